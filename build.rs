@@ -1,6 +1,16 @@
 // This file was automatically generated.
 
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
 fn main() {
+    // Our own memory.x, which keeps code out of the flash sectors used for data
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
 
@@ -0,0 +1,248 @@
+//! Least-squares fits of a correction to captured reference points
+//!
+//! A linear fit finds gain and offset, a polynomial fit a residual
+//! polynomial with unit gain, and a table passes through the points
+//! themselves. Every fit is checked against the points it came from.
+
+use crate::{
+    CalibrationError, ChannelCalibration, Correction, MAX_POLYNOMIAL_TERMS, MAX_TABLE_POINTS,
+};
+
+/// Fits leaving a larger error at any point are rejected, in counts
+pub const MAX_FIT_RESIDUAL_COUNTS: i32 = 64;
+
+/// Measured counts are scaled by this for the polynomial fit, which keeps
+/// the normal equations well-conditioned
+const POLYNOMIAL_SCALE: f64 = 32768.0;
+
+/// Form of the fitted correction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FitKind {
+    /// Offset only from one point, least-squares gain and offset from more
+    Linear,
+    /// Residual polynomial of up to cubic order, one order below the points
+    Polynomial,
+    /// Lookup table through the points themselves
+    Table,
+}
+
+/// One captured reference point, in counts of 1/128°C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalibrationPoint {
+    /// Mean uncalibrated counts
+    pub measured: i32,
+    /// Reference temperature given by the operator
+    pub reference: i32,
+    /// Standard deviation of the captured samples
+    pub stddev: u32,
+}
+
+/// Fitted correction, not yet committed
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fit {
+    pub kind: FitKind,
+    pub offset_counts: i32,
+    pub gain: f32,
+    pub correction: Correction,
+    /// Largest error left at any captured point, in counts
+    pub max_residual_counts: i32,
+}
+
+impl Fit {
+    /// Fit a correction of the given kind to `points` and check it misses
+    /// none of them by more than `MAX_FIT_RESIDUAL_COUNTS`
+    pub fn new(kind: FitKind, points: &[CalibrationPoint]) -> Result<Self, FitError> {
+        let (offset_counts, gain, correction) = match kind {
+            FitKind::Linear => fit_linear(points)?,
+            FitKind::Polynomial => fit_polynomial(points)?,
+            FitKind::Table => fit_table(points)?,
+        };
+        let calibration = ChannelCalibration {
+            offset_counts,
+            gain,
+            correction,
+            ..ChannelCalibration::IDENTITY
+        };
+        calibration.validate()?;
+
+        let max_residual_counts = points
+            .iter()
+            .map(|point| (calibration.apply(point.measured) - point.reference).abs())
+            .max()
+            .unwrap_or(0);
+        if max_residual_counts > MAX_FIT_RESIDUAL_COUNTS {
+            return Err(FitError::PoorFit);
+        }
+
+        Ok(Self {
+            kind,
+            offset_counts,
+            gain,
+            correction,
+            max_residual_counts,
+        })
+    }
+
+    /// Calibration record for this fit
+    pub fn calibration(&self, id: u32, date: u32, cj_offset_counts: i32) -> ChannelCalibration {
+        ChannelCalibration {
+            id,
+            date,
+            offset_counts: self.offset_counts,
+            gain: self.gain,
+            cj_offset_counts,
+            correction: self.correction,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FitError {
+    /// The fit needs more points than were given
+    NotEnoughPoints,
+    /// Two points have the same measured counts
+    Degenerate,
+    /// The fit misses a point by more than `MAX_FIT_RESIDUAL_COUNTS`
+    PoorFit,
+    Invalid(CalibrationError),
+}
+
+impl From<CalibrationError> for FitError {
+    fn from(error: CalibrationError) -> Self {
+        FitError::Invalid(error)
+    }
+}
+
+/// Offset from one point, least-squares gain and offset from two or more
+fn fit_linear(points: &[CalibrationPoint]) -> Result<(i32, f32, Correction), FitError> {
+    match points {
+        [] => Err(FitError::NotEnoughPoints),
+        [point] => Ok((point.reference - point.measured, 1.0, Correction::None)),
+        _ => {
+            let n = points.len() as f64;
+            let mean_x = points.iter().map(|p| p.measured as f64).sum::<f64>() / n;
+            let mean_y = points.iter().map(|p| p.reference as f64).sum::<f64>() / n;
+            let mut sxx = 0.0;
+            let mut sxy = 0.0;
+            for point in points {
+                let dx = point.measured as f64 - mean_x;
+                sxx += dx * dx;
+                sxy += dx * (point.reference as f64 - mean_y);
+            }
+            if sxx == 0.0 {
+                return Err(FitError::Degenerate);
+            }
+            let gain = sxy / sxx;
+            let offset = mean_y - gain * mean_x;
+            Ok((round_f64(offset), gain as f32, Correction::None))
+        }
+    }
+}
+
+/// Least-squares residual polynomial with unit gain and no offset
+///
+/// Uses at least one point more than the polynomial has terms, so the fit
+/// cannot simply pass through every point and the residual check means
+/// something.
+fn fit_polynomial(points: &[CalibrationPoint]) -> Result<(i32, f32, Correction), FitError> {
+    if points.len() < 2 {
+        return Err(FitError::NotEnoughPoints);
+    }
+    let terms = (points.len() - 1).min(MAX_POLYNOMIAL_TERMS);
+
+    // Normal equations in u = measured / scale, augmented with the right-hand side
+    let mut matrix = [[0.0f64; MAX_POLYNOMIAL_TERMS + 1]; MAX_POLYNOMIAL_TERMS];
+    for point in points {
+        let u = point.measured as f64 / POLYNOMIAL_SCALE;
+        let residual = (point.reference - point.measured) as f64;
+        let mut powers = [1.0f64; 2 * MAX_POLYNOMIAL_TERMS - 1];
+        for k in 1..powers.len() {
+            powers[k] = powers[k - 1] * u;
+        }
+        for (row, values) in matrix.iter_mut().enumerate().take(terms) {
+            for (column, value) in values.iter_mut().enumerate().take(terms) {
+                *value += powers[row + column];
+            }
+            values[MAX_POLYNOMIAL_TERMS] += residual * powers[row];
+        }
+    }
+
+    let solution = solve(&mut matrix, terms).ok_or(FitError::Degenerate)?;
+
+    // Undo the scaling: c_k = a_k / scale^k
+    let mut coefficients = [0.0f32; MAX_POLYNOMIAL_TERMS];
+    let mut scale = 1.0;
+    for (coefficient, a) in coefficients.iter_mut().zip(&solution[..terms]) {
+        *coefficient = (a / scale) as f32;
+        scale *= POLYNOMIAL_SCALE;
+    }
+    Ok((0, 1.0, Correction::Polynomial(coefficients)))
+}
+
+/// Gauss-Jordan elimination with partial pivoting on the first `terms` rows
+fn solve(
+    matrix: &mut [[f64; MAX_POLYNOMIAL_TERMS + 1]; MAX_POLYNOMIAL_TERMS],
+    terms: usize,
+) -> Option<[f64; MAX_POLYNOMIAL_TERMS]> {
+    const RHS: usize = MAX_POLYNOMIAL_TERMS;
+    for column in 0..terms {
+        let pivot = (column..terms)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        let pivot_row = matrix[column];
+        for (row, values) in matrix.iter_mut().enumerate().take(terms) {
+            if row != column {
+                let factor = values[column] / pivot_row[column];
+                for (value, pivot_value) in values.iter_mut().zip(&pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let mut solution = [0.0; MAX_POLYNOMIAL_TERMS];
+    for (row, value) in solution.iter_mut().enumerate().take(terms) {
+        *value = matrix[row][RHS] / matrix[row][row];
+    }
+    Some(solution)
+}
+
+/// Lookup table through the points, sorted by measured counts
+fn fit_table(points: &[CalibrationPoint]) -> Result<(i32, f32, Correction), FitError> {
+    if points.len() < 2 {
+        return Err(FitError::NotEnoughPoints);
+    }
+    let mut table = [(0, 0); MAX_TABLE_POINTS];
+    for (entry, point) in table.iter_mut().zip(points) {
+        *entry = (point.measured, point.reference);
+    }
+    let len = points.len();
+    table[..len].sort_unstable_by_key(|&(measured, _)| measured);
+    if table[..len].windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(FitError::Degenerate);
+    }
+    Ok((
+        0,
+        1.0,
+        Correction::Table {
+            points: table,
+            len: len as u8,
+        },
+    ))
+}
+
+/// Round to the nearest count, saturating
+fn round_f64(value: f64) -> i32 {
+    let rounded = if value >= 0.0 {
+        value + 0.5
+    } else {
+        value - 0.5
+    };
+    rounded as i32
+}
@@ -0,0 +1,159 @@
+//! Per-channel calibration corrections and the fits that produce them
+//!
+//! Each channel's counts are corrected as `gain * counts + offset`, then by
+//! an optional residual polynomial or a piecewise-linear lookup table.
+//! Corrections run in f32 on the FPU; a 19-bit count fits the f32 mantissa
+//! exactly. The MAX31856 cold-junction offset is part of the record but is
+//! applied by the converter itself.
+//!
+//! The crate is `no_std` and independent of the board, so the corrections
+//! and the least-squares fits in `fit` can be checked on the host.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+pub mod fit;
+
+/// Maximum residual polynomial terms (up to cubic)
+pub const MAX_POLYNOMIAL_TERMS: usize = 4;
+
+/// Maximum lookup table points
+pub const MAX_TABLE_POINTS: usize = 8;
+
+/// Gains outside this range are rejected as implausible
+const MIN_GAIN: f32 = 0.5;
+const MAX_GAIN: f32 = 2.0;
+
+/// Correction applied after offset and gain
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Correction {
+    None,
+    /// Residual c0 + c1*x + c2*x^2 + c3*x^3 added to x, all in counts
+    Polynomial([f32; MAX_POLYNOMIAL_TERMS]),
+    /// Piecewise-linear map of (measured, true) counts, sorted by measured
+    /// counts; the end segments are extended beyond the table
+    Table {
+        points: [(i32, i32); MAX_TABLE_POINTS],
+        len: u8,
+    },
+}
+
+/// Calibration of one channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelCalibration {
+    /// Identifier of the calibration run, 0 if uncalibrated
+    pub id: u32,
+    /// Calibration date as YYYYMMDD, 0 if uncalibrated
+    pub date: u32,
+    /// Added after the gain, in counts
+    pub offset_counts: i32,
+    pub gain: f32,
+    /// Cold-junction offset applied by the converter, in counts
+    pub cj_offset_counts: i32,
+    pub correction: Correction,
+}
+
+impl ChannelCalibration {
+    /// Leave counts unchanged
+    pub const IDENTITY: ChannelCalibration = ChannelCalibration {
+        id: 0,
+        date: 0,
+        offset_counts: 0,
+        gain: 1.0,
+        cj_offset_counts: 0,
+        correction: Correction::None,
+    };
+
+    /// Check the record is usable before applying or storing it
+    pub fn validate(&self) -> Result<(), CalibrationError> {
+        if !(MIN_GAIN..=MAX_GAIN).contains(&self.gain) {
+            return Err(CalibrationError::InvalidGain);
+        }
+        match self.correction {
+            Correction::None => Ok(()),
+            Correction::Polynomial(coefficients) => {
+                if coefficients.iter().all(|c| c.is_finite()) {
+                    Ok(())
+                } else {
+                    Err(CalibrationError::InvalidCoefficient)
+                }
+            }
+            Correction::Table { points, len } => {
+                let len = len as usize;
+                if !(2..=MAX_TABLE_POINTS).contains(&len)
+                    || points[..len].windows(2).any(|pair| pair[0].0 >= pair[1].0)
+                {
+                    Err(CalibrationError::InvalidTable)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Correct one reading's counts
+    pub fn apply(&self, counts: i32) -> i32 {
+        let x = self.gain * counts as f32 + self.offset_counts as f32;
+        let corrected = match self.correction {
+            Correction::None => x,
+            Correction::Polynomial(c) => x + (c[0] + x * (c[1] + x * (c[2] + x * c[3]))),
+            Correction::Table { points, len } => interpolate(&points[..len as usize], x),
+        };
+        round_counts(corrected)
+    }
+}
+
+/// Round to the nearest count, saturating
+fn round_counts(value: f32) -> i32 {
+    let rounded = if value >= 0.0 {
+        value + 0.5
+    } else {
+        value - 0.5
+    };
+    rounded as i32
+}
+
+/// Piecewise-linear interpolation through sorted (measured, true) points
+fn interpolate(points: &[(i32, i32)], x: f32) -> f32 {
+    // Pick the segment containing x, or the nearest end segment
+    let segment = points
+        .windows(2)
+        .position(|pair| x < pair[1].0 as f32)
+        .unwrap_or(points.len() - 2);
+    let (x0, y0) = points[segment];
+    let (x1, y1) = points[segment + 1];
+    y0 as f32 + (x - x0 as f32) * (y1 - y0) as f32 / (x1 - x0) as f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CalibrationError {
+    InvalidGain,
+    InvalidCoefficient,
+    /// Fewer than two points, too many, or not strictly increasing
+    InvalidTable,
+    /// No calibration stored (erased or foreign data)
+    NotFound,
+    UnsupportedVersion,
+    ChannelCountMismatch,
+    ChecksumMismatch,
+    /// Flash read, erase or write failed
+    Storage,
+}
+
+impl CalibrationError {
+    pub const fn message(&self) -> &'static str {
+        match self {
+            CalibrationError::InvalidGain => "gain out of range",
+            CalibrationError::InvalidCoefficient => "invalid coefficient",
+            CalibrationError::InvalidTable => "invalid table",
+            CalibrationError::NotFound => "no stored calibration",
+            CalibrationError::UnsupportedVersion => "unsupported version",
+            CalibrationError::ChannelCountMismatch => "channel count mismatch",
+            CalibrationError::ChecksumMismatch => "checksum mismatch",
+            CalibrationError::Storage => "flash error",
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Least-squares fits and the corrections they produce
+//!
+//! Counts are 1/128 °C, so `MAX_FIT_RESIDUAL_COUNTS` (64) is 0.5 °C.
+
+use thermosoft_calibration::fit::{
+    CalibrationPoint, Fit, FitError, FitKind, MAX_FIT_RESIDUAL_COUNTS,
+};
+use thermosoft_calibration::{ChannelCalibration, Correction};
+
+fn points(pairs: &[(i32, i32)]) -> Vec<CalibrationPoint> {
+    pairs
+        .iter()
+        .map(|&(measured, reference)| CalibrationPoint {
+            measured,
+            reference,
+            stddev: 0,
+        })
+        .collect()
+}
+
+fn calibration(fit: &Fit) -> ChannelCalibration {
+    fit.calibration(1, 20260101, 0)
+}
+
+#[test]
+fn linear_fit_through_two_points() {
+    let fit = Fit::new(FitKind::Linear, &points(&[(1000, 1100), (3000, 3150)])).unwrap();
+    assert!((fit.gain - 1.025).abs() < 1e-6, "gain {}", fit.gain);
+    assert_eq!(fit.offset_counts, 75);
+    assert_eq!(fit.correction, Correction::None);
+    assert_eq!(fit.max_residual_counts, 0);
+    assert_eq!(calibration(&fit).apply(2000), 2125);
+}
+
+#[test]
+fn linear_fit_from_one_point_is_an_offset() {
+    let fit = Fit::new(FitKind::Linear, &points(&[(3200, 3180)])).unwrap();
+    assert_eq!((fit.offset_counts, fit.gain), (-20, 1.0));
+}
+
+#[test]
+fn polynomial_fit_recovers_a_cubic_residual() {
+    // Residual 20 + 8k + 2k^2 - k^3 at x = 8000k, so every reference is whole
+    let expected = [20.0, 1e-3, 3.125e-8, -1.953125e-12];
+    let pairs: Vec<_> = (0..6)
+        .map(|k| {
+            let residual = 20 + 8 * k + 2 * k * k - k * k * k;
+            (8000 * k, 8000 * k + residual)
+        })
+        .collect();
+    let fit = Fit::new(FitKind::Polynomial, &points(&pairs)).unwrap();
+    let Correction::Polynomial(coefficients) = fit.correction else {
+        panic!("{:?}", fit.correction);
+    };
+    for (found, expected) in coefficients.iter().zip(expected) {
+        let error = (*found as f64 - expected).abs() / expected.abs();
+        assert!(error < 1e-4, "{coefficients:?}");
+    }
+    assert_eq!((fit.offset_counts, fit.gain), (0, 1.0));
+    assert_eq!(fit.max_residual_counts, 0);
+    // Between points: 20 + 12 + 4.5 - 3.375 = 33.125 at k = 1.5
+    assert_eq!(calibration(&fit).apply(12_000), 12_033);
+}
+
+#[test]
+fn polynomial_fit_uses_one_term_fewer_than_points() {
+    // Two points leave room for a constant only, their mean residual
+    let fit = Fit::new(FitKind::Polynomial, &points(&[(1000, 1010), (5000, 5030)])).unwrap();
+    assert_eq!(
+        fit.correction,
+        Correction::Polynomial([20.0, 0.0, 0.0, 0.0])
+    );
+    assert_eq!(fit.max_residual_counts, 10);
+}
+
+#[test]
+fn singular_fit_is_refused() {
+    let same = points(&[(2000, 2000), (2000, 2010), (2000, 2020)]);
+    assert_eq!(
+        Fit::new(FitKind::Polynomial, &same),
+        Err(FitError::Degenerate)
+    );
+    assert_eq!(Fit::new(FitKind::Linear, &same), Err(FitError::Degenerate));
+    assert_eq!(Fit::new(FitKind::Table, &same), Err(FitError::Degenerate));
+}
+
+#[test]
+fn too_few_points_are_refused() {
+    let one = points(&[(2000, 2000)]);
+    assert_eq!(
+        Fit::new(FitKind::Linear, &[]),
+        Err(FitError::NotEnoughPoints)
+    );
+    assert_eq!(
+        Fit::new(FitKind::Polynomial, &one),
+        Err(FitError::NotEnoughPoints)
+    );
+    assert_eq!(
+        Fit::new(FitKind::Table, &one),
+        Err(FitError::NotEnoughPoints)
+    );
+}
+
+#[test]
+fn fit_missing_a_point_by_more_than_half_a_degree_is_refused() {
+    // A straight line misses the middle point by two thirds of its offset
+    // and the ends by a third
+    let within = points(&[(1000, 1000), (2000, 2090), (3000, 3000)]);
+    let fit = Fit::new(FitKind::Linear, &within).unwrap();
+    assert!(fit.max_residual_counts <= MAX_FIT_RESIDUAL_COUNTS);
+
+    let beyond = points(&[(1000, 1000), (2000, 2200), (3000, 3000)]);
+    assert_eq!(Fit::new(FitKind::Linear, &beyond), Err(FitError::PoorFit));
+}
+
+#[test]
+fn table_interpolates_between_and_beyond_its_points() {
+    // Given out of order; the table sorts them by measured counts
+    let fit = Fit::new(
+        FitKind::Table,
+        &points(&[(3000, 3100), (1000, 1000), (2000, 2050)]),
+    )
+    .unwrap();
+    assert_eq!(fit.max_residual_counts, 0);
+    let calibration = calibration(&fit);
+    assert_eq!(calibration.apply(1500), 1525);
+    assert_eq!(calibration.apply(2500), 2575);
+    // The end segments are extended
+    assert_eq!(calibration.apply(4000), 4150);
+    assert_eq!(calibration.apply(0), -50);
+}
@@ -0,0 +1,288 @@
+//! Fixed-point FIR/IIR filtering of thermocouple counts
+//!
+//! Counts are the 19-bit MAX31856 values, filtered in integer arithmetic:
+//! coefficients are Q2.30 (range [-2, 2)) and products are accumulated in
+//! 64 bits, so a 19-bit input never overflows the accumulator. The pipeline
+//! per channel is FIR -> cascaded biquads -> decimator. Outlier rejection,
+//! which runs before the filters, is in `outlier`.
+//!
+//! The crate is `no_std` and independent of the board, so the arithmetic
+//! can be checked on the host against a floating-point reference.
+
+#![no_std]
+#![deny(unsafe_code)]
+
+pub mod outlier;
+
+/// Fractional bits of the Q2.30 coefficient format
+pub const COEFF_FRAC_BITS: u32 = 30;
+
+/// Maximum FIR length per channel
+pub const MAX_FIR_TAPS: usize = 32;
+
+/// Maximum number of cascaded biquad stages per channel
+pub const MAX_BIQUAD_STAGES: usize = 4;
+
+/// Convert a real coefficient to Q2.30, rounding to nearest
+pub const fn q30(value: f64) -> i32 {
+    let scaled = value * (1u64 << COEFF_FRAC_BITS) as f64;
+    if scaled >= 0.0 {
+        (scaled + 0.5) as i32
+    } else {
+        (scaled - 0.5) as i32
+    }
+}
+
+/// Scale a Q2.30 accumulator back to counts, rounding to nearest
+fn round_q30(acc: i64) -> i32 {
+    let rounded = (acc + (1 << (COEFF_FRAC_BITS - 1))) >> COEFF_FRAC_BITS;
+    rounded.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Coefficients of one biquad stage in Q2.30
+/// y[n] = b0 x[n] + b1 x[n-1] + b2 x[n-2] - a1 y[n-1] - a2 y[n-2]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BiquadCoefficients {
+    pub b0: i32,
+    pub b1: i32,
+    pub b2: i32,
+    pub a1: i32,
+    pub a2: i32,
+}
+
+/// Per-channel filter selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FilterConfig {
+    /// FIR taps in Q2.30, empty for no FIR stage
+    pub fir_taps: &'static [i32],
+    /// Biquad stages applied after the FIR, empty for no IIR stage
+    pub biquads: &'static [BiquadCoefficients],
+}
+
+impl FilterConfig {
+    /// Pass counts through unchanged
+    pub const BYPASS: FilterConfig = FilterConfig {
+        fir_taps: &[],
+        biquads: &[],
+    };
+
+    pub const fn is_bypass(&self) -> bool {
+        self.fir_taps.is_empty() && self.biquads.is_empty()
+    }
+
+    /// 8-tap moving average
+    pub const MOVING_AVERAGE_8: FilterConfig = FilterConfig {
+        fir_taps: &[q30(0.125); 8],
+        biquads: &[],
+    };
+
+    /// 2nd-order Butterworth low-pass at 0.1 x sample rate
+    pub const BUTTERWORTH_LP_0_1: FilterConfig = FilterConfig {
+        fir_taps: &[],
+        biquads: &[BiquadCoefficients {
+            b0: q30(0.067_455_273_889_071_91),
+            b1: q30(0.134_910_547_778_143_82),
+            b2: q30(0.067_455_273_889_071_91),
+            a1: q30(-1.142_980_502_539_901_1),
+            a2: q30(0.412_801_598_096_188_6),
+        }],
+    };
+
+    /// 2nd-order Butterworth low-pass at 0.02 x sample rate
+    pub const BUTTERWORTH_LP_0_02: FilterConfig = FilterConfig {
+        fir_taps: &[],
+        biquads: &[BiquadCoefficients {
+            b0: q30(0.003_621_681_514_928_641_7),
+            b1: q30(0.007_243_363_029_857_283),
+            b2: q30(0.003_621_681_514_928_641_7),
+            a1: q30(-1.822_694_925_196_308_3),
+            a2: q30(0.837_181_651_256_022_6),
+        }],
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FilterError {
+    TooManyTaps,
+    TooManyStages,
+    ZeroDecimation,
+    NoSuchChannel,
+}
+
+/// Filters that can be selected by name at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FilterPreset {
+    Bypass,
+    MovingAverage8,
+    ButterworthLp0_1,
+    ButterworthLp0_02,
+}
+
+impl FilterPreset {
+    /// Look a preset up by its command name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bypass" => Some(FilterPreset::Bypass),
+            "avg8" => Some(FilterPreset::MovingAverage8),
+            "lp0.1" => Some(FilterPreset::ButterworthLp0_1),
+            "lp0.02" => Some(FilterPreset::ButterworthLp0_02),
+            _ => None,
+        }
+    }
+
+    pub const fn config(self) -> FilterConfig {
+        match self {
+            FilterPreset::Bypass => FilterConfig::BYPASS,
+            FilterPreset::MovingAverage8 => FilterConfig::MOVING_AVERAGE_8,
+            FilterPreset::ButterworthLp0_1 => FilterConfig::BUTTERWORTH_LP_0_1,
+            FilterPreset::ButterworthLp0_02 => FilterConfig::BUTTERWORTH_LP_0_02,
+        }
+    }
+}
+
+/// Extra fractional bits carried in the biquad state
+/// Keeps round-off from being amplified by poles close to the unit circle;
+/// 19-bit counts shifted by 8 still fit comfortably in an i32
+const STATE_GUARD_BITS: u32 = 8;
+
+/// Direct form I state of one biquad stage, in counts << STATE_GUARD_BITS
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: i32,
+    x2: i32,
+    y1: i32,
+    y2: i32,
+}
+
+impl BiquadState {
+    fn process(&mut self, c: &BiquadCoefficients, counts: i32) -> i32 {
+        let x = counts << STATE_GUARD_BITS;
+        let acc =
+            c.b0 as i64 * x as i64 + c.b1 as i64 * self.x1 as i64 + c.b2 as i64 * self.x2 as i64
+                - c.a1 as i64 * self.y1 as i64
+                - c.a2 as i64 * self.y2 as i64;
+        let y = round_q30(acc);
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        (y + (1 << (STATE_GUARD_BITS - 1))) >> STATE_GUARD_BITS
+    }
+}
+
+/// FIR and IIR state for one channel
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelFilter {
+    config: FilterConfig,
+    history: [i32; MAX_FIR_TAPS],
+    head: usize,
+    stages: [BiquadState; MAX_BIQUAD_STAGES],
+}
+
+impl Default for ChannelFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChannelFilter {
+    pub const fn new() -> Self {
+        Self {
+            config: FilterConfig::BYPASS,
+            history: [0; MAX_FIR_TAPS],
+            head: 0,
+            stages: [BiquadState {
+                x1: 0,
+                x2: 0,
+                y1: 0,
+                y2: 0,
+            }; MAX_BIQUAD_STAGES],
+        }
+    }
+
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    /// Select a new filter, clearing all state
+    pub fn set_config(&mut self, config: FilterConfig) -> Result<(), FilterError> {
+        if config.fir_taps.len() > MAX_FIR_TAPS {
+            return Err(FilterError::TooManyTaps);
+        }
+        if config.biquads.len() > MAX_BIQUAD_STAGES {
+            return Err(FilterError::TooManyStages);
+        }
+        *self = Self::new();
+        self.config = config;
+        Ok(())
+    }
+
+    /// Clear filter history, e.g. after a fault
+    pub fn reset(&mut self) {
+        let config = self.config;
+        *self = Self::new();
+        self.config = config;
+    }
+
+    /// Filter one sample of counts
+    pub fn process(&mut self, counts: i32) -> i32 {
+        let mut y = counts;
+
+        let taps = self.config.fir_taps;
+        if !taps.is_empty() {
+            self.history[self.head] = y;
+            // taps[0] multiplies the newest sample
+            let mut acc = 0i64;
+            let mut index = self.head;
+            for &tap in taps {
+                acc += tap as i64 * self.history[index] as i64;
+                index = if index == 0 {
+                    taps.len() - 1
+                } else {
+                    index - 1
+                };
+            }
+            self.head = (self.head + 1) % taps.len();
+            y = round_q30(acc);
+        }
+
+        for (stage, coefficients) in self.stages.iter_mut().zip(self.config.biquads) {
+            y = stage.process(coefficients, y);
+        }
+
+        y
+    }
+}
+
+/// Keeps every `factor`-th sample of a stream, starting with the last of
+/// the first `factor`
+#[derive(Debug, Clone, Copy)]
+pub struct Decimator {
+    factor: u16,
+    phase: u16,
+}
+
+impl Decimator {
+    pub const fn new(factor: u16) -> Result<Self, FilterError> {
+        if factor == 0 {
+            return Err(FilterError::ZeroDecimation);
+        }
+        Ok(Self { factor, phase: 0 })
+    }
+
+    /// Count one sample, returning whether it is kept
+    pub fn keep(&mut self) -> bool {
+        self.phase += 1;
+        if self.phase >= self.factor {
+            self.phase = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Outlier rejection with median and rate-of-change checks
+//!
+//! A sample is implausible if it sits further than a threshold from the
+//! median of recent samples (a single-sample spike), or if it moved further
+//! than the maximum slew rate allows since the last accepted sample. The
+//! decision is always recorded in the reading's quality flags; whether the
+//! value is also replaced depends on the configured action.
+
+use thermosoft_protocol::QualityFlags;
+
+/// Longest supported median window
+pub const MAX_MEDIAN_WINDOW: usize = 9;
+
+/// What to do with an implausible sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutlierAction {
+    /// Keep the value, only set the quality flag
+    Flag,
+    /// Substitute the median (spike) or last accepted value (slew)
+    Replace,
+}
+
+/// Per-channel outlier rejection settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutlierConfig {
+    /// Median window length, odd, up to `MAX_MEDIAN_WINDOW` (0 disables)
+    pub median_window: usize,
+    /// Largest allowed deviation from the median, in counts
+    pub spike_threshold_counts: u32,
+    /// Largest plausible rate of change in counts per second (0 disables)
+    pub max_slew_counts_per_s: u32,
+    /// Consecutive slew rejections after which a genuine step is accepted
+    /// (0 for no limit: a step is never accepted)
+    pub max_consecutive_rejects: u8,
+    pub action: OutlierAction,
+}
+
+impl OutlierConfig {
+    pub const DISABLED: OutlierConfig = OutlierConfig {
+        median_window: 0,
+        spike_threshold_counts: 0,
+        max_slew_counts_per_s: 0,
+        max_consecutive_rejects: 0,
+        action: OutlierAction::Flag,
+    };
+
+    /// Check the median window, which must be 0 or odd and at most
+    /// `MAX_MEDIAN_WINDOW`
+    pub const fn validate(&self) -> Result<(), OutlierError> {
+        if self.median_window > MAX_MEDIAN_WINDOW {
+            return Err(OutlierError::MedianWindowTooLong);
+        }
+        if self.median_window != 0 && self.median_window.is_multiple_of(2) {
+            return Err(OutlierError::EvenMedianWindow);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutlierError {
+    /// The median window is longer than `MAX_MEDIAN_WINDOW`
+    MedianWindowTooLong,
+    /// The median window has no middle sample
+    EvenMedianWindow,
+}
+
+/// Outlier rejection state for one channel
+#[derive(Debug, Clone, Copy)]
+pub struct OutlierFilter {
+    config: OutlierConfig,
+    window: [i32; MAX_MEDIAN_WINDOW],
+    filled: usize,
+    head: usize,
+    /// Last accepted value and the time it was sampled, in microseconds
+    last_accepted: Option<(i32, u64)>,
+    rejects: u8,
+}
+
+impl OutlierFilter {
+    pub const fn new(config: OutlierConfig) -> Result<Self, OutlierError> {
+        match config.validate() {
+            Ok(()) => Ok(Self::with_valid_config(config)),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Build a filter from a config that already passed `validate`
+    pub const fn with_valid_config(config: OutlierConfig) -> Self {
+        Self {
+            config,
+            window: [0; MAX_MEDIAN_WINDOW],
+            filled: 0,
+            head: 0,
+            last_accepted: None,
+            rejects: 0,
+        }
+    }
+
+    pub fn config(&self) -> &OutlierConfig {
+        &self.config
+    }
+
+    /// Clear history, e.g. after a fault
+    pub fn reset(&mut self) {
+        *self = Self::with_valid_config(self.config);
+    }
+
+    fn median(&self) -> Option<i32> {
+        let len = self.config.median_window;
+        if len == 0 || self.filled < len {
+            return None;
+        }
+        let mut sorted = self.window;
+        let sorted = &mut sorted[..len];
+        sorted.sort_unstable();
+        Some(sorted[len / 2])
+    }
+
+    /// Check one sample taken at `timestamp_us`
+    /// Returns the (possibly replaced) value and the quality decision. The
+    /// slew limit scales with the time since the last accepted sample, so a
+    /// genuine ramp is accepted again after a rejection or a pause
+    pub fn process(&mut self, counts: i32, timestamp_us: u64) -> (i32, QualityFlags) {
+        let mut value = counts;
+        let mut flags = QualityFlags::NONE;
+        let replace = self.config.action == OutlierAction::Replace;
+
+        let len = self.config.median_window;
+        if len > 0 {
+            self.window[self.head] = counts;
+            self.head = (self.head + 1) % len;
+            self.filled = (self.filled + 1).min(len);
+
+            if let Some(median) = self.median()
+                && counts.abs_diff(median) > self.config.spike_threshold_counts
+            {
+                flags.insert(QualityFlags::SPIKE);
+                if replace {
+                    value = median;
+                    flags.insert(QualityFlags::REPLACED);
+                }
+            }
+        }
+
+        if self.config.max_slew_counts_per_s > 0
+            && let Some((last, last_us)) = self.last_accepted
+        {
+            let dt_us = timestamp_us.saturating_sub(last_us);
+            let max_step =
+                (self.config.max_slew_counts_per_s as u64).saturating_mul(dt_us) / 1_000_000;
+            let limit = self.config.max_consecutive_rejects;
+            if value.abs_diff(last) as u64 > max_step && (limit == 0 || self.rejects < limit) {
+                self.rejects = self.rejects.saturating_add(1);
+                flags.insert(QualityFlags::SLEW);
+                if replace {
+                    value = last;
+                    flags.insert(QualityFlags::REPLACED);
+                }
+                return (value, flags);
+            }
+        }
+
+        self.rejects = 0;
+        if !flags.contains(QualityFlags::SPIKE) || replace {
+            self.last_accepted = Some((value, timestamp_us));
+        }
+        (value, flags)
+    }
+}
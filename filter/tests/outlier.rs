@@ -0,0 +1,110 @@
+//! Median and slew checks of the outlier filter
+//!
+//! Samples are 10 ms apart unless a test says otherwise. The slew limit is
+//! 1000 counts/s, so one period allows a step of 10 counts.
+
+use thermosoft_filter::outlier::{
+    MAX_MEDIAN_WINDOW, OutlierAction, OutlierConfig, OutlierError, OutlierFilter,
+};
+use thermosoft_protocol::QualityFlags;
+
+const PERIOD_US: u64 = 10_000;
+
+fn slew_only(action: OutlierAction, max_consecutive_rejects: u8) -> OutlierFilter {
+    OutlierFilter::new(OutlierConfig {
+        max_slew_counts_per_s: 1000,
+        max_consecutive_rejects,
+        action,
+        ..OutlierConfig::DISABLED
+    })
+    .unwrap()
+}
+
+#[test]
+fn ramp_within_slew_rate_passes() {
+    let mut filter = slew_only(OutlierAction::Replace, 0);
+    for n in 0..100 {
+        let (value, flags) = filter.process(n * 10, n as u64 * PERIOD_US);
+        assert_eq!((value, flags), (n * 10, QualityFlags::NONE), "sample {n}");
+    }
+}
+
+#[test]
+fn ramp_recovers_after_a_rejection() {
+    for action in [OutlierAction::Flag, OutlierAction::Replace] {
+        let mut filter = slew_only(action, 0);
+        filter.process(0, 0);
+        // A glitch far off the ramp is rejected
+        let (_, flags) = filter.process(5000, PERIOD_US);
+        assert!(flags.contains(QualityFlags::SLEW), "{action:?}");
+        // The ramp carries on at the slew limit and must be accepted again,
+        // even with no limit on consecutive rejections
+        for n in 2..50 {
+            let counts = n * 10;
+            let (value, flags) = filter.process(counts, n as u64 * PERIOD_US);
+            assert_eq!(
+                (value, flags),
+                (counts, QualityFlags::NONE),
+                "{action:?} {n}"
+            );
+        }
+    }
+}
+
+#[test]
+fn step_after_a_pause_is_scaled_to_the_gap() {
+    let mut filter = slew_only(OutlierAction::Replace, 0);
+    filter.process(0, 0);
+    // 500 ms without samples allows 500 counts, not one period's 10
+    let (value, flags) = filter.process(400, 500_000);
+    assert_eq!((value, flags), (400, QualityFlags::NONE));
+    let (value, flags) = filter.process(1000, 510_000);
+    assert_eq!(value, 400);
+    assert!(flags.contains(QualityFlags::SLEW | QualityFlags::REPLACED));
+}
+
+#[test]
+fn genuine_step_accepted_after_reject_limit() {
+    let mut filter = slew_only(OutlierAction::Replace, 3);
+    filter.process(0, 0);
+    for n in 1..=3 {
+        let (value, flags) = filter.process(5000, n * PERIOD_US);
+        assert_eq!(value, 0);
+        assert!(flags.contains(QualityFlags::SLEW), "sample {n}");
+    }
+    let (value, flags) = filter.process(5000, 4 * PERIOD_US);
+    assert_eq!((value, flags), (5000, QualityFlags::NONE));
+}
+
+#[test]
+fn spike_replaced_by_median() {
+    let mut filter = OutlierFilter::new(OutlierConfig {
+        median_window: 3,
+        spike_threshold_counts: 50,
+        action: OutlierAction::Replace,
+        ..OutlierConfig::DISABLED
+    })
+    .unwrap();
+    filter.process(100, 0);
+    filter.process(102, PERIOD_US);
+    let (value, flags) = filter.process(900, 2 * PERIOD_US);
+    assert_eq!(value, 102);
+    assert!(flags.contains(QualityFlags::SPIKE | QualityFlags::REPLACED));
+}
+
+#[test]
+fn median_window_is_validated() {
+    let config = |median_window| OutlierConfig {
+        median_window,
+        ..OutlierConfig::DISABLED
+    };
+    assert_eq!(
+        OutlierFilter::new(config(MAX_MEDIAN_WINDOW + 2)).err(),
+        Some(OutlierError::MedianWindowTooLong)
+    );
+    assert_eq!(
+        OutlierFilter::new(config(4)).err(),
+        Some(OutlierError::EvenMedianWindow)
+    );
+    assert!(OutlierFilter::new(config(MAX_MEDIAN_WINDOW)).is_ok());
+}
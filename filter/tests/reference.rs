@@ -0,0 +1,190 @@
+//! The fixed-point filters against a floating-point reference
+//!
+//! The reference uses the same Q2.30 coefficients converted back to `f64`,
+//! so the comparison covers only the integer arithmetic. Outputs are
+//! rounded to whole counts and the biquad state keeps 8 guard bits, so the
+//! fixed-point result must stay within `TOLERANCE_COUNTS` of the reference.
+
+use thermosoft_filter::{
+    BiquadCoefficients, COEFF_FRAC_BITS, ChannelFilter, Decimator, FilterConfig, FilterError,
+    FilterPreset, MAX_FIR_TAPS, q30,
+};
+
+/// Largest allowed difference from the reference, in counts (1/128 °C)
+const TOLERANCE_COUNTS: f64 = 1.0;
+
+/// Samples fed through each filter
+const SAMPLES: usize = 4000;
+
+fn real(coefficient: i32) -> f64 {
+    coefficient as f64 / (1u64 << COEFF_FRAC_BITS) as f64
+}
+
+/// Floating-point FIR followed by direct form I biquads
+struct Reference {
+    taps: Vec<f64>,
+    history: Vec<f64>,
+    stages: Vec<([f64; 5], [f64; 4])>,
+}
+
+impl Reference {
+    fn new(config: &FilterConfig) -> Self {
+        Self {
+            taps: config.fir_taps.iter().copied().map(real).collect(),
+            history: vec![0.0; config.fir_taps.len()],
+            stages: config
+                .biquads
+                .iter()
+                .map(|c| ([c.b0, c.b1, c.b2, c.a1, c.a2].map(real), [0.0; 4]))
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let mut y = x;
+        if !self.taps.is_empty() {
+            self.history.rotate_right(1);
+            self.history[0] = y;
+            y = self
+                .taps
+                .iter()
+                .zip(&self.history)
+                .map(|(t, x)| t * x)
+                .sum();
+        }
+        for ([b0, b1, b2, a1, a2], [x1, x2, y1, y2]) in &mut self.stages {
+            let out = *b0 * y + *b1 * *x1 + *b2 * *x2 - *a1 * *y1 - *a2 * *y2;
+            (*x2, *x1, *y2, *y1) = (*x1, y, *y1, out);
+            y = out;
+        }
+        y
+    }
+}
+
+/// A thermocouple-like signal in counts: steps across most of the 19-bit
+/// range, a slow swing and some deterministic noise
+fn signal(n: usize) -> i32 {
+    let level = match n / 500 % 4 {
+        0 => 3_200.0,
+        1 => 160_000.0,
+        2 => -25_000.0,
+        _ => 64_000.0,
+    };
+    let t = n as f64;
+    let swing = 4_000.0 * (t / 150.0).sin();
+    let noise = 37.0 * (t * 1.7).sin() + 11.0 * (t * 5.3).cos();
+    (level + swing + noise).round() as i32
+}
+
+fn assert_matches_reference(config: FilterConfig) {
+    let mut filter = ChannelFilter::new();
+    filter.set_config(config).unwrap();
+    let mut reference = Reference::new(&config);
+    for n in 0..SAMPLES {
+        let x = signal(n);
+        let fixed = filter.process(x) as f64;
+        let exact = reference.process(x as f64);
+        assert!(
+            (fixed - exact).abs() <= TOLERANCE_COUNTS,
+            "sample {n}: fixed point {fixed}, reference {exact:.3}"
+        );
+    }
+}
+
+#[test]
+fn bypass_passes_counts_through() {
+    let mut filter = ChannelFilter::new();
+    for n in 0..100 {
+        assert_eq!(filter.process(signal(n)), signal(n));
+    }
+}
+
+#[test]
+fn moving_average_matches_reference() {
+    assert_matches_reference(FilterConfig::MOVING_AVERAGE_8);
+}
+
+#[test]
+fn longest_fir_matches_reference() {
+    // A windowed low-pass with taps of both signs
+    const TAPS: [i32; MAX_FIR_TAPS] = {
+        let mut taps = [0; MAX_FIR_TAPS];
+        let mut i = 0;
+        while i < MAX_FIR_TAPS {
+            let weight = [-0.004, 0.011, 0.046, 0.071, 0.046, 0.011, -0.004, 0.0][i % 8];
+            taps[i] = q30(weight);
+            i += 1;
+        }
+        taps
+    };
+    assert_matches_reference(FilterConfig {
+        fir_taps: &TAPS,
+        biquads: &[],
+    });
+}
+
+#[test]
+fn butterworth_biquads_match_reference() {
+    assert_matches_reference(FilterConfig::BUTTERWORTH_LP_0_1);
+    assert_matches_reference(FilterConfig::BUTTERWORTH_LP_0_02);
+}
+
+#[test]
+fn fir_into_cascaded_biquads_matches_reference() {
+    const STAGES: [BiquadCoefficients; 2] = [
+        FilterConfig::BUTTERWORTH_LP_0_1.biquads[0],
+        FilterConfig::BUTTERWORTH_LP_0_02.biquads[0],
+    ];
+    assert_matches_reference(FilterConfig {
+        fir_taps: FilterConfig::MOVING_AVERAGE_8.fir_taps,
+        biquads: &STAGES,
+    });
+}
+
+#[test]
+fn decimated_output_matches_reference() {
+    for factor in [1, 3, 10] {
+        let config = FilterConfig::BUTTERWORTH_LP_0_02;
+        let mut filter = ChannelFilter::new();
+        filter.set_config(config).unwrap();
+        let mut decimator = Decimator::new(factor).unwrap();
+        let mut reference = Reference::new(&config);
+
+        let mut kept = 0;
+        for n in 0..SAMPLES {
+            let x = signal(n);
+            let fixed = filter.process(x) as f64;
+            let exact = reference.process(x as f64);
+            // The last sample of every group of `factor` is kept
+            let expected = (n + 1) % factor as usize == 0;
+            assert_eq!(decimator.keep(), expected, "sample {n}, factor {factor}");
+            if expected {
+                kept += 1;
+                assert!((fixed - exact).abs() <= TOLERANCE_COUNTS);
+            }
+        }
+        assert_eq!(kept, SAMPLES / factor as usize);
+    }
+}
+
+#[test]
+fn invalid_configs_are_refused() {
+    assert_eq!(Decimator::new(0).unwrap_err(), FilterError::ZeroDecimation);
+    let mut filter = ChannelFilter::new();
+    assert_eq!(
+        filter.set_config(FilterConfig {
+            fir_taps: &[0; MAX_FIR_TAPS + 1],
+            biquads: &[],
+        }),
+        Err(FilterError::TooManyTaps)
+    );
+}
+
+#[test]
+fn presets_are_found_by_name() {
+    assert_eq!(
+        FilterPreset::from_name("lp0.02").map(FilterPreset::config),
+        Some(FilterConfig::BUTTERWORTH_LP_0_02)
+    );
+    assert_eq!(FilterPreset::from_name("lowpass"), None);
+}
@@ -0,0 +1,483 @@
+//! The `dashboard` command: a live terminal view of every board heard from
+//!
+//! Each board gets a table with one row per channel: the latest value, the
+//! minimum and maximum since the dashboard started, a sparkline of recent
+//! samples, the converter fault state and the board's packet loss. Loss is
+//! counted per board, since every packet carries all of a board's channels.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use thermosoft_protocol::host::{Packet, Payload, StatusPayload, counts_to_celsius};
+use thermosoft_protocol::{QualityFlags, Sample};
+
+use crate::sequence::{Arrival, LinkCounters, SequenceTracker};
+
+/// Largest datagram accepted, above any board's packet size
+const MAX_DATAGRAM_LEN: usize = 65_536;
+
+/// A board is shown as silent after this long without a packet
+const SILENT_AFTER: Duration = Duration::from_secs(3);
+
+/// Sparkline levels, lowest first
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Fault flags shown in the fault column, with their labels
+const FAULTS: [(QualityFlags, &str); 6] = [
+    (QualityFlags::OPEN, "OPEN"),
+    (QualityFlags::OVUV, "OV/UV"),
+    (QualityFlags::TC_RANGE, "TC RANGE"),
+    (QualityFlags::CJ_RANGE, "CJ RANGE"),
+    (QualityFlags::SPI_ERROR, "SPI"),
+    (QualityFlags::STALE, "STALE"),
+];
+
+#[derive(Args)]
+pub struct DashboardArgs {
+    /// Address and port to listen on
+    #[arg(long, default_value = "0.0.0.0:1684")]
+    listen: SocketAddr,
+    /// Samples of history per channel in the sparkline
+    #[arg(long, default_value_t = 40)]
+    history: usize,
+    /// Milliseconds between screen refreshes
+    #[arg(long, default_value_t = 200)]
+    refresh_ms: u64,
+}
+
+/// What the dashboard knows about one channel
+#[derive(Debug, Default)]
+struct ChannelView {
+    latest: Option<Sample>,
+    /// Extremes of the valid samples, in counts
+    min: Option<i32>,
+    max: Option<i32>,
+    /// Recent samples, `None` where a sample was not valid
+    history: VecDeque<Option<i32>>,
+}
+
+impl ChannelView {
+    fn add(&mut self, sample: Sample, history: usize) {
+        let valid = sample.is_valid();
+        if valid {
+            self.min = Some(self.min.map_or(sample.counts, |min| min.min(sample.counts)));
+            self.max = Some(self.max.map_or(sample.counts, |max| max.max(sample.counts)));
+        }
+        self.history.push_back(valid.then_some(sample.counts));
+        while self.history.len() > history {
+            self.history.pop_front();
+        }
+        self.latest = Some(sample);
+    }
+
+    /// Recent samples as block characters, scaled to their own range
+    fn sparkline(&self) -> String {
+        let valid = self.history.iter().flatten();
+        let (Some(&low), Some(&high)) = (valid.clone().min(), valid.max()) else {
+            return String::new();
+        };
+        let span = (high - low).max(1) as i64;
+        self.history
+            .iter()
+            .map(|counts| match counts {
+                Some(counts) => {
+                    let level = (*counts - low) as i64 * (SPARK_LEVELS.len() as i64 - 1) / span;
+                    SPARK_LEVELS[level as usize]
+                }
+                None => ' ',
+            })
+            .collect()
+    }
+
+    /// Faults of the latest sample, or "ok"
+    fn fault_text(&self) -> String {
+        let Some(sample) = self.latest else {
+            return String::new();
+        };
+        let faults: Vec<&str> = FAULTS
+            .iter()
+            .filter(|(flag, _)| sample.quality.contains(*flag))
+            .map(|&(_, label)| label)
+            .collect();
+        if faults.is_empty() && sample.is_valid() {
+            "ok".to_owned()
+        } else if faults.is_empty() {
+            "INVALID".to_owned()
+        } else {
+            faults.join(" ")
+        }
+    }
+}
+
+/// What the dashboard knows about one board
+#[derive(Debug)]
+struct BoardView {
+    channels: Vec<ChannelView>,
+    status: Option<StatusPayload>,
+    last_seen: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Dashboard {
+    boards: BTreeMap<u32, BoardView>,
+    tracker: SequenceTracker,
+    rejected: u64,
+    history: usize,
+}
+
+impl Dashboard {
+    fn receive(&mut self, datagram: &[u8]) {
+        let Ok(packet) = Packet::parse(datagram) else {
+            self.rejected += 1;
+            return;
+        };
+        let header = packet.header;
+        let arrival = self
+            .tracker
+            .record(header.board_id, header.epoch, header.sequence);
+        if arrival == Arrival::Duplicate {
+            return;
+        }
+        let board = self
+            .boards
+            .entry(header.board_id)
+            .or_insert_with(|| BoardView {
+                channels: Vec::new(),
+                status: None,
+                last_seen: Instant::now(),
+            });
+        board.last_seen = Instant::now();
+        // A restarted board may have been rebuilt with other channels
+        board
+            .channels
+            .resize_with(header.channel_count as usize, ChannelView::default);
+
+        match packet.payload {
+            Payload::Data(data) => {
+                for (channel, sample) in data.samples() {
+                    if let Some(view) = board.channels.get_mut(channel) {
+                        view.add(sample, self.history);
+                    }
+                }
+            }
+            Payload::Status(status) => board.status = Some(status),
+            Payload::Summary(_) | Payload::Event(_) => {}
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, listen: SocketAddr) {
+        let footer = format!(
+            "Listening on {listen} · {} board(s) · {} rejected datagram(s) · q to quit",
+            self.boards.len(),
+            self.rejected
+        );
+        if self.boards.is_empty() {
+            let [body, bottom] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+            frame.render_widget(
+                Paragraph::new("Waiting for packets...").block(Block::bordered()),
+                body,
+            );
+            frame.render_widget(Line::from(footer), bottom);
+            return;
+        }
+
+        // Each board's table: borders, header row and one row per channel
+        let mut constraints: Vec<Constraint> = self
+            .boards
+            .values()
+            .map(|board| Constraint::Length(board.channels.len() as u16 + 3))
+            .collect();
+        constraints.push(Constraint::Min(0));
+        constraints.push(Constraint::Length(1));
+        let areas = Layout::vertical(constraints).split(frame.area());
+
+        for ((board_id, board), area) in self.boards.iter().zip(areas.iter()) {
+            frame.render_widget(self.board_table(*board_id, board), *area);
+        }
+        frame.render_widget(Line::from(footer), areas[areas.len() - 1]);
+    }
+
+    fn board_table(&self, board_id: u32, board: &BoardView) -> Table<'_> {
+        let (title, loss) = match self.tracker.board(board_id) {
+            Some(sequence) => {
+                let counters = sequence.counters;
+                let loss = loss_percent(&counters);
+                let mut title = format!(
+                    " Board {board_id:08x} · epoch {} · {} packets · {} lost · {} reordered · {} restarts ",
+                    sequence.epoch,
+                    counters.received,
+                    counters.lost,
+                    counters.reordered,
+                    counters.restarts
+                );
+                if let Some(status) = &board.status {
+                    title += &format!(
+                        "· board: {} send errors, {} queue drops ",
+                        status.send_errors + status.send_timeouts,
+                        status.queue_drops
+                    );
+                }
+                (title, loss)
+            }
+            None => (format!(" Board {board_id:08x} "), 0.0),
+        };
+        let silent = board.last_seen.elapsed();
+        let border = if silent >= SILENT_AFTER {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        let mut block = Block::bordered().title(title).border_style(border);
+        if silent >= SILENT_AFTER {
+            block = block.title_bottom(format!(" silent for {}s ", silent.as_secs()));
+        }
+
+        let celsius = |counts: Option<i32>| {
+            counts.map_or(String::new(), |counts| {
+                format!("{:.2}", counts_to_celsius(counts))
+            })
+        };
+        let rows = board.channels.iter().enumerate().map(|(index, channel)| {
+            let fault = channel.fault_text();
+            let fault_style = if fault == "ok" {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Red)
+            };
+            Row::new(vec![
+                Cell::from(format!("TC{index}")),
+                Cell::from(celsius(
+                    channel
+                        .latest
+                        .filter(Sample::is_valid)
+                        .map(|sample| sample.counts),
+                )),
+                Cell::from(celsius(channel.min)),
+                Cell::from(celsius(channel.max)),
+                Cell::from(fault).style(fault_style),
+                Cell::from(format!("{loss:.1}%")),
+                Cell::from(channel.sparkline()),
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Length(5),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(20),
+                Constraint::Length(7),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new([
+                "Ch", "Now °C", "Min °C", "Max °C", "Fault", "Loss", "History",
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(block)
+    }
+}
+
+/// Share of a board's packets that were lost, in percent
+fn loss_percent(counters: &LinkCounters) -> f64 {
+    let expected = counters.received + counters.lost;
+    counters.lost as f64 * 100.0 / expected.max(1) as f64
+}
+
+pub fn run(args: &DashboardArgs) -> io::Result<()> {
+    let socket = UdpSocket::bind(args.listen)?;
+    socket.set_nonblocking(true)?;
+    let mut terminal = ratatui::try_init()?;
+    let result = show(&mut terminal, &socket, args);
+    ratatui::restore();
+    result
+}
+
+fn show(
+    terminal: &mut DefaultTerminal,
+    socket: &UdpSocket,
+    args: &DashboardArgs,
+) -> io::Result<()> {
+    let mut dashboard = Dashboard {
+        history: args.history.max(1),
+        ..Dashboard::default()
+    };
+    let mut buffer = vec![0u8; MAX_DATAGRAM_LEN];
+    let refresh = Duration::from_millis(args.refresh_ms);
+    loop {
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((length, _)) => dashboard.receive(&buffer[..length]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+        terminal.draw(|frame| dashboard.draw(frame, args.listen))?;
+
+        if event::poll(refresh)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use thermosoft_protocol::{PacketFormat, PacketSource, SensorDataPacket, frame_packet};
+
+    use super::*;
+
+    const CHANNELS: usize = 2;
+
+    fn sample(counts: i32, quality: QualityFlags) -> Sample {
+        Sample {
+            counts,
+            timestamp_us: 0,
+            quality,
+        }
+    }
+
+    fn valid(counts: i32) -> Sample {
+        sample(counts, QualityFlags::VALID)
+    }
+
+    /// A framed data packet carrying `slots` sample sets, oldest first
+    fn data<const B: usize>(
+        board_id: u32,
+        sequence: u32,
+        slots: [[Sample; CHANNELS]; B],
+    ) -> Vec<u8> {
+        let mut packet = SensorDataPacket::<CHANNELS, B>::new();
+        for slot in slots {
+            packet.store(&slot.map(Some));
+        }
+        let mut payload = vec![0u8; SensorDataPacket::<CHANNELS, B>::ENCODED_LEN];
+        let length = packet.encode_into(&mut payload).unwrap();
+        let source = PacketSource { board_id, epoch: 1 };
+        let mut frame = vec![0u8; length + 64];
+        let length = frame_packet::<CHANNELS, B>(
+            PacketFormat::Versioned,
+            &source,
+            sequence,
+            &payload[..length],
+            &mut frame,
+        );
+        frame.truncate(length);
+        frame
+    }
+
+    fn dashboard(history: usize) -> Dashboard {
+        Dashboard {
+            history,
+            ..Dashboard::default()
+        }
+    }
+
+    fn channel(dashboard: &Dashboard, board_id: u32, channel: usize) -> &ChannelView {
+        &dashboard.boards[&board_id].channels[channel]
+    }
+
+    #[test]
+    fn min_and_max_cover_valid_samples_only() {
+        let mut dashboard = dashboard(10);
+        let open = sample(0, QualityFlags::OPEN);
+        dashboard.receive(&data(
+            1,
+            0,
+            [[valid(100), valid(7)], [open, open], [valid(300), open]],
+        ));
+        dashboard.receive(&data(
+            1,
+            1,
+            [[valid(50), open], [valid(200), open], [open, open]],
+        ));
+
+        let view = channel(&dashboard, 1, 0);
+        assert_eq!((view.min, view.max), (Some(50), Some(300)));
+        assert_eq!(view.latest.map(|s| s.quality), Some(QualityFlags::OPEN));
+        let view = channel(&dashboard, 1, 1);
+        assert_eq!((view.min, view.max), (Some(7), Some(7)));
+    }
+
+    #[test]
+    fn history_keeps_the_latest_samples() {
+        let mut dashboard = dashboard(4);
+        for sequence in 0..3 {
+            let base = sequence as i32 * 3;
+            let slots = [0, 1, 2].map(|slot| [valid(base + slot), valid(0)]);
+            dashboard.receive(&data(1, sequence, slots));
+        }
+        let history: Vec<_> = channel(&dashboard, 1, 0).history.iter().copied().collect();
+        assert_eq!(history, [Some(5), Some(6), Some(7), Some(8)]);
+    }
+
+    #[test]
+    fn sparkline_scales_to_its_own_range() {
+        let view = |history: &[Option<i32>]| ChannelView {
+            history: history.iter().copied().collect(),
+            ..ChannelView::default()
+        };
+        assert_eq!(view(&[Some(0), Some(70), Some(35)]).sparkline(), "▁█▄");
+        assert_eq!(view(&[Some(-10), Some(-10), Some(-10)]).sparkline(), "▁▁▁");
+        assert_eq!(view(&[Some(10), None, Some(20)]).sparkline(), "▁ █");
+        assert_eq!(view(&[None, None]).sparkline(), "");
+        assert_eq!(view(&[]).sparkline(), "");
+    }
+
+    #[test]
+    fn fault_text_names_the_latest_faults() {
+        let mut view = ChannelView::default();
+        assert_eq!(view.fault_text(), "");
+        view.add(valid(100), 4);
+        assert_eq!(view.fault_text(), "ok");
+        view.add(sample(0, QualityFlags::OPEN | QualityFlags::OVUV), 4);
+        assert_eq!(view.fault_text(), "OPEN OV/UV");
+        view.add(sample(0, QualityFlags::SPI_ERROR), 4);
+        assert_eq!(view.fault_text(), "SPI");
+        view.add(sample(0, QualityFlags::NONE), 4);
+        assert_eq!(view.fault_text(), "INVALID");
+    }
+
+    #[test]
+    fn boards_are_tracked_separately() {
+        let mut dashboard = dashboard(10);
+        let set = |counts| [[valid(counts), valid(counts + 1)]];
+        // Board 1 loses #2; board 2 loses nothing and repeats #1
+        for sequence in [0, 1, 3] {
+            dashboard.receive(&data(1, sequence, set(100)));
+        }
+        for sequence in [0, 1, 1] {
+            dashboard.receive(&data(2, sequence, set(200)));
+        }
+        dashboard.receive(b"not a packet");
+
+        assert_eq!(dashboard.boards.len(), 2);
+        assert_eq!(dashboard.rejected, 1);
+        let counters = |board_id| dashboard.tracker.board(board_id).unwrap().counters;
+        assert_eq!(loss_percent(&counters(1)), 25.0);
+        assert_eq!(loss_percent(&counters(2)), 0.0);
+        assert_eq!(counters(2).duplicates, 1);
+
+        // The duplicate adds nothing to the view
+        assert_eq!(channel(&dashboard, 1, 0).history.len(), 3);
+        assert_eq!(channel(&dashboard, 2, 0).history.len(), 2);
+        assert_eq!(channel(&dashboard, 1, 1).latest.unwrap().counts, 101);
+        assert_eq!(channel(&dashboard, 2, 1).latest.unwrap().counts, 201);
+    }
+}
@@ -0,0 +1,48 @@
+//! Host tools for ThermoSoft boards
+//!
+//! `record` listens for board packets and writes their samples to CSV or
+//! JSON Lines files. `dashboard` shows them live in the terminal.
+//! `simulate` sends synthetic packets, so both can be tried without hardware.
+
+mod dashboard;
+mod output;
+mod record;
+mod sequence;
+mod simulate;
+mod time;
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(version, about = "Host tools for ThermoSoft data acquisition boards")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Record the UDP stream of one or more boards to files
+    Record(record::RecordArgs),
+    /// Show live values, faults and packet loss of every board
+    Dashboard(dashboard::DashboardArgs),
+    /// Send synthetic board packets, for testing a receiver
+    Simulate(simulate::SimulateArgs),
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Record(args) => record::run(&args),
+        Command::Dashboard(args) => dashboard::run(&args),
+        Command::Simulate(args) => simulate::run(&args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
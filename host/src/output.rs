@@ -0,0 +1,168 @@
+//! Sample files in CSV or JSON Lines, rotated by size or age
+//!
+//! Every file starts afresh (with a header row for CSV), so each can be
+//! read on its own. A packet's samples are never split across files.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::ValueEnum;
+use thermosoft_protocol::PacketHeader;
+use thermosoft_protocol::host::DataPayload;
+
+use crate::time::{file_stamp, iso8601};
+
+const CSV_HEADER: &str =
+    "host_time,board_id,epoch,sequence,channel,timestamp_us,counts,celsius,quality,valid\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// When to start a new file; `None` limits are not applied
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rotation {
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+/// Writes every sample of received data packets, one row each
+pub struct SampleWriter {
+    directory: PathBuf,
+    prefix: String,
+    format: Format,
+    rotation: Rotation,
+    file: Option<BufWriter<File>>,
+    written: u64,
+    opened: Instant,
+}
+
+impl SampleWriter {
+    pub fn new(directory: &Path, prefix: &str, format: Format, rotation: Rotation) -> Self {
+        Self {
+            directory: directory.to_owned(),
+            prefix: prefix.to_owned(),
+            format,
+            rotation,
+            file: None,
+            written: 0,
+            opened: Instant::now(),
+        }
+    }
+
+    /// Append the samples of one data packet received at `received`
+    pub fn write_packet(
+        &mut self,
+        received: SystemTime,
+        header: &PacketHeader,
+        data: &DataPayload,
+    ) -> io::Result<()> {
+        if self.file.is_none() || self.rotation_due() {
+            self.open(received)?;
+        }
+        let host_time = iso8601(received);
+        let mut rows = String::new();
+        for (channel, sample) in data.samples() {
+            let row = match self.format {
+                Format::Csv => format!(
+                    "{},{:08x},{},{},{},{},{},{},{:04x},{}\n",
+                    host_time,
+                    header.board_id,
+                    header.epoch,
+                    header.sequence,
+                    channel,
+                    sample.timestamp_us,
+                    sample.counts,
+                    sample.celsius(),
+                    sample.quality.bits(),
+                    sample.is_valid() as u8,
+                ),
+                Format::Jsonl => format!(
+                    "{{\"host_time\":\"{}\",\"board_id\":\"{:08x}\",\"epoch\":{},\"sequence\":{},\
+                     \"channel\":{},\"timestamp_us\":{},\"counts\":{},\"celsius\":{},\
+                     \"quality\":{},\"valid\":{}}}\n",
+                    host_time,
+                    header.board_id,
+                    header.epoch,
+                    header.sequence,
+                    channel,
+                    sample.timestamp_us,
+                    sample.counts,
+                    sample.celsius(),
+                    sample.quality.bits(),
+                    sample.is_valid(),
+                ),
+            };
+            rows.push_str(&row);
+        }
+        self.write(&rows)?;
+        self.flush()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn rotation_due(&self) -> bool {
+        self.rotation
+            .max_bytes
+            .is_some_and(|max| self.written >= max)
+            || self
+                .rotation
+                .max_age
+                .is_some_and(|max| self.opened.elapsed() >= max)
+    }
+
+    /// Close the current file and start the next one
+    fn open(&mut self, now: SystemTime) -> io::Result<()> {
+        self.flush()?;
+        let stamp = file_stamp(now);
+        let extension = self.format.extension();
+        let mut attempt = 0;
+        let (path, file) = loop {
+            // Files rotated within the same second get a counter
+            let name = match attempt {
+                0 => format!("{}-{stamp}.{extension}", self.prefix),
+                n => format!("{}-{stamp}-{n}.{extension}", self.prefix),
+            };
+            let path = self.directory.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => attempt += 1,
+                Err(error) => return Err(error),
+            }
+        };
+        eprintln!("writing {}", path.display());
+        self.file = Some(BufWriter::new(file));
+        self.written = 0;
+        self.opened = Instant::now();
+        if self.format == Format::Csv {
+            self.write(CSV_HEADER)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.write_all(text.as_bytes())?;
+            self.written += text.len() as u64;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+//! The `record` command: receive, check and store board packets
+
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::Args;
+use thermosoft_protocol::crc::{CRC32_TEST_VECTORS, crc32};
+use thermosoft_protocol::host::{Packet, ParseError, Payload, StatusPayload};
+use thermosoft_protocol::{
+    EVENT_ALARM_CLEARED, EVENT_ALARM_RAISED, EVENT_INTERLOCK_RELEASED, EVENT_INTERLOCK_TRIPPED,
+    EVENT_TRIGGER_ASSERTED, EVENT_TRIGGER_RELEASED, EventPacket,
+};
+
+use crate::output::{Format, Rotation, SampleWriter};
+use crate::sequence::{Arrival, SequenceTracker};
+
+/// How long a receive waits before housekeeping runs
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Largest datagram accepted, above any board's packet size
+const MAX_DATAGRAM_LEN: usize = 65_536;
+
+#[derive(Args)]
+pub struct RecordArgs {
+    /// Address and port to listen on
+    #[arg(long, default_value = "0.0.0.0:1684")]
+    listen: SocketAddr,
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Directory to write files into
+    #[arg(long, default_value = ".")]
+    out: PathBuf,
+    /// Start of each file name, followed by the time it was opened
+    #[arg(long, default_value = "thermosoft")]
+    prefix: String,
+    /// Start a new file once the current one reaches this many MiB
+    #[arg(long)]
+    rotate_mib: Option<u64>,
+    /// Start a new file after this many minutes
+    #[arg(long)]
+    rotate_minutes: Option<u64>,
+    /// Seconds between loss reports, 0 for none
+    #[arg(long, default_value_t = 10)]
+    report_secs: u64,
+    /// Stop after this many seconds
+    #[arg(long)]
+    duration_secs: Option<u64>,
+}
+
+/// Datagrams that could not be decoded at all
+#[derive(Debug, Default)]
+struct RejectCounters {
+    no_header: u64,
+    bad_crc: u64,
+    unsupported: u64,
+}
+
+pub fn run(args: &RecordArgs) -> io::Result<()> {
+    check_crc()?;
+    let socket = UdpSocket::bind(args.listen)?;
+    socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+    eprintln!("listening on {}", socket.local_addr()?);
+
+    let rotation = Rotation {
+        max_bytes: args.rotate_mib.map(|mib| mib * 1024 * 1024),
+        max_age: args
+            .rotate_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+    };
+    let mut writer = SampleWriter::new(&args.out, &args.prefix, args.format, rotation);
+    let mut tracker = SequenceTracker::default();
+    let mut statuses = BTreeMap::new();
+    let mut rejects = RejectCounters::default();
+
+    let started = Instant::now();
+    let mut last_report = started;
+    let mut buffer = vec![0u8; MAX_DATAGRAM_LEN];
+    loop {
+        if args
+            .duration_secs
+            .is_some_and(|secs| started.elapsed() >= Duration::from_secs(secs))
+        {
+            break;
+        }
+        if args.report_secs > 0 && last_report.elapsed() >= Duration::from_secs(args.report_secs) {
+            last_report = Instant::now();
+            report(&tracker, &statuses, &rejects);
+        }
+
+        let (length, sender) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        let received = SystemTime::now();
+
+        let packet = match Packet::parse(&buffer[..length]) {
+            Ok(packet) => packet,
+            Err(error) => {
+                match error {
+                    ParseError::NoHeader => rejects.no_header += 1,
+                    ParseError::BadCrc => rejects.bad_crc += 1,
+                    _ => rejects.unsupported += 1,
+                }
+                eprintln!("{sender}: dropped {length}-byte datagram: {error}");
+                continue;
+            }
+        };
+        let header = packet.header;
+
+        match tracker.record(header.board_id, header.epoch, header.sequence) {
+            Arrival::First => eprintln!(
+                "board {:08x} at {sender}: epoch {}, {} channels, batches of {}",
+                header.board_id, header.epoch, header.channel_count, header.batch_size
+            ),
+            Arrival::Gap(lost) => eprintln!(
+                "board {:08x}: {lost} packet(s) lost before #{}",
+                header.board_id, header.sequence
+            ),
+            Arrival::Late => eprintln!(
+                "board {:08x}: #{} arrived out of order",
+                header.board_id, header.sequence
+            ),
+            Arrival::Duplicate => {
+                eprintln!(
+                    "board {:08x}: duplicate #{} ignored",
+                    header.board_id, header.sequence
+                );
+                continue;
+            }
+            Arrival::Restart => eprintln!(
+                "board {:08x}: restarted, epoch {}, from #{}",
+                header.board_id, header.epoch, header.sequence
+            ),
+            Arrival::Stale => eprintln!(
+                "board {:08x}: #{} of epoch {} arrived after the restart",
+                header.board_id, header.sequence, header.epoch
+            ),
+            Arrival::InOrder => {}
+        }
+
+        match packet.payload {
+            Payload::Data(data) => writer.write_packet(received, &header, &data)?,
+            Payload::Event(event) => {
+                eprintln!("board {:08x}: {}", header.board_id, describe_event(&event))
+            }
+            Payload::Status(status) => {
+                statuses.insert(header.board_id, status);
+            }
+            Payload::Summary(_) => {}
+        }
+    }
+
+    writer.flush()?;
+    report(&tracker, &statuses, &rejects);
+    Ok(())
+}
+
+/// Refuse to run if the CRC disagrees with the protocol's known answers
+fn check_crc() -> io::Result<()> {
+    for &(data, expected) in CRC32_TEST_VECTORS {
+        if crc32(data) != expected {
+            return Err(io::Error::other(format!(
+                "CRC-32 self-test failed for a {}-byte vector",
+                data.len()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn report(
+    tracker: &SequenceTracker,
+    statuses: &BTreeMap<u32, StatusPayload>,
+    rejects: &RejectCounters,
+) {
+    for (board_id, board) in tracker.boards() {
+        let counters = board.counters;
+        let mut line = format!(
+            "board {board_id:08x} epoch {}: {} received, {} lost, {} reordered, {} duplicate, {} restarts",
+            board.epoch,
+            counters.received,
+            counters.lost,
+            counters.reordered,
+            counters.duplicates,
+            counters.restarts
+        );
+        if let Some(status) = statuses.get(board_id) {
+            line += &format!(
+                "; board reports {} send errors, {} timeouts, {} queue drops, {} overruns",
+                status.send_errors, status.send_timeouts, status.queue_drops, status.overruns
+            );
+        }
+        eprintln!("{line}");
+    }
+    if rejects.no_header + rejects.bad_crc + rejects.unsupported > 0 {
+        eprintln!(
+            "rejected: {} without header, {} bad CRC, {} unsupported",
+            rejects.no_header, rejects.bad_crc, rejects.unsupported
+        );
+    }
+}
+
+fn describe_event(event: &EventPacket) -> String {
+    let at = event.timestamp_us;
+    match event.event_kind {
+        EVENT_ALARM_RAISED | EVENT_ALARM_CLEARED => format!(
+            "alarm {} {} on channel {} at {at} us, value {}",
+            event.source_id,
+            if event.event_kind == EVENT_ALARM_RAISED {
+                "raised"
+            } else {
+                "cleared"
+            },
+            event.channel,
+            event.value
+        ),
+        EVENT_INTERLOCK_TRIPPED => format!(
+            "interlock {} tripped at {at} us{}",
+            event.source_id,
+            if event.value != 0 { " by a fault" } else { "" }
+        ),
+        EVENT_INTERLOCK_RELEASED => format!("interlock {} released at {at} us", event.source_id),
+        EVENT_TRIGGER_ASSERTED => format!("trigger asserted at {at} us"),
+        EVENT_TRIGGER_RELEASED => format!("trigger released at {at} us"),
+        kind => format!("unknown event kind {kind} at {at} us"),
+    }
+}
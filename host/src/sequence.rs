@@ -0,0 +1,172 @@
+//! Packet loss and reordering, from the header's epoch and sequence number
+//!
+//! Each board numbers every packet it sends since boot, so a gap is a lost
+//! packet and a number below the highest seen is a late one. A new epoch
+//! means the board restarted and its numbering began again. So does a jump
+//! back by more than any reordering could explain, since a board that cannot
+//! store its boot count restarts in the same epoch, and a lower epoch, since
+//! the boot count can be erased or the board swapped. Only packets from just
+//! before the last restart count as stale.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Lost sequence numbers remembered per board, so late arrivals can be
+/// told from duplicates
+const MAX_MISSING: usize = 4096;
+
+/// Furthest a received packet can fall behind the highest and still be a
+/// duplicate; anything further back, and not missing, is a restart
+const REORDER_WINDOW: u32 = 64;
+
+/// What one packet meant for its board's sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// The first packet seen from this board
+    First,
+    /// The next expected packet
+    InOrder,
+    /// Arrived after this many packets were skipped
+    Gap(u32),
+    /// One of the skipped packets, arriving late
+    Late,
+    /// Already received
+    Duplicate,
+    /// From a new epoch, or far behind in the same one; the board restarted
+    Restart,
+    /// Sent shortly before the board's last restart, arriving after it
+    Stale,
+}
+
+/// Per-board counts since the receiver started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkCounters {
+    pub received: u64,
+    /// Packets skipped and not (yet) received late
+    pub lost: u64,
+    /// Packets that arrived after a later one
+    pub reordered: u64,
+    pub duplicates: u64,
+    pub restarts: u64,
+}
+
+/// Sequence state of one board
+#[derive(Debug, Clone)]
+pub struct BoardSequence {
+    pub epoch: u32,
+    next: u32,
+    missing: BTreeSet<u32>,
+    /// Where the board was when it last restarted
+    previous: Option<PreviousRun>,
+    pub counters: LinkCounters,
+}
+
+/// Sequence state of a board's run before its last restart
+#[derive(Debug, Clone)]
+struct PreviousRun {
+    epoch: u32,
+    next: u32,
+    missing: BTreeSet<u32>,
+}
+
+impl BoardSequence {
+    fn new(epoch: u32, sequence: u32) -> Self {
+        Self {
+            epoch,
+            next: sequence.wrapping_add(1),
+            missing: BTreeSet::new(),
+            previous: None,
+            counters: LinkCounters {
+                received: 1,
+                ..LinkCounters::default()
+            },
+        }
+    }
+
+    fn record(&mut self, epoch: u32, sequence: u32) -> Arrival {
+        self.counters.received += 1;
+        if epoch != self.epoch {
+            if let Some(previous) = &mut self.previous
+                && epoch == previous.epoch
+                && previous.next.wrapping_sub(sequence).wrapping_sub(1) < REORDER_WINDOW
+            {
+                if previous.missing.remove(&sequence) {
+                    self.counters.lost -= 1;
+                }
+                return Arrival::Stale;
+            }
+            return self.restart(epoch, sequence);
+        }
+
+        // Distance ahead of the expected number; sequence numbers wrap
+        let ahead = sequence.wrapping_sub(self.next);
+        if ahead == 0 {
+            self.next = sequence.wrapping_add(1);
+            Arrival::InOrder
+        } else if ahead < u32::MAX / 2 {
+            let remembered = ahead.min(MAX_MISSING as u32);
+            for skipped in 0..remembered {
+                self.missing.insert(sequence.wrapping_sub(skipped + 1));
+            }
+            while self.missing.len() > MAX_MISSING {
+                self.missing.pop_first();
+            }
+            self.counters.lost += ahead as u64;
+            self.next = sequence.wrapping_add(1);
+            Arrival::Gap(ahead)
+        } else if self.missing.remove(&sequence) {
+            self.counters.lost -= 1;
+            self.counters.reordered += 1;
+            Arrival::Late
+        } else if self.next.wrapping_sub(sequence) <= REORDER_WINDOW {
+            self.counters.duplicates += 1;
+            Arrival::Duplicate
+        } else {
+            self.restart(epoch, sequence)
+        }
+    }
+
+    /// Start counting afresh from `sequence`, keeping the counters
+    fn restart(&mut self, epoch: u32, sequence: u32) -> Arrival {
+        let counters = self.counters;
+        let previous = PreviousRun {
+            epoch: self.epoch,
+            next: self.next,
+            missing: std::mem::take(&mut self.missing),
+        };
+        *self = Self::new(epoch, sequence);
+        self.previous = Some(previous);
+        self.counters = LinkCounters {
+            restarts: counters.restarts + 1,
+            ..counters
+        };
+        Arrival::Restart
+    }
+}
+
+/// Sequence state of every board heard from, by board ID
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    boards: BTreeMap<u32, BoardSequence>,
+}
+
+impl SequenceTracker {
+    /// Account for a packet from `board_id`
+    pub fn record(&mut self, board_id: u32, epoch: u32, sequence: u32) -> Arrival {
+        match self.boards.get_mut(&board_id) {
+            Some(board) => board.record(epoch, sequence),
+            None => {
+                self.boards
+                    .insert(board_id, BoardSequence::new(epoch, sequence));
+                Arrival::First
+            }
+        }
+    }
+
+    pub fn board(&self, board_id: u32) -> Option<&BoardSequence> {
+        self.boards.get(&board_id)
+    }
+
+    pub fn boards(&self) -> impl Iterator<Item = (&u32, &BoardSequence)> {
+        self.boards.iter()
+    }
+}
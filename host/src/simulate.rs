@@ -0,0 +1,215 @@
+//! The `simulate` command: stand-in boards sending synthetic packets
+//!
+//! Each simulated board streams data packets of slowly varying
+//! temperatures and a status packet every second, framed exactly as the
+//! firmware does. Packets can be dropped or swapped and samples faulted on
+//! purpose, to exercise a receiver's loss, reorder and fault handling.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::Args;
+use thermosoft_protocol::{
+    COUNTS_PER_DEGREE_C, PACKET_TAG_STATUS, PacketFormat, PacketSource, QualityFlags, Sample,
+    SensorDataPacket, StatusPacket, frame_packet,
+};
+
+// Layout of the simulated boards, as in the default firmware build
+const CHANNEL_COUNT: usize = 4;
+const BATCH_SIZE: usize = 10;
+
+/// First simulated board ID; further boards count up from it
+const FIRST_BOARD_ID: u32 = 0x5100_0000;
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// Receiver address
+    #[arg(long, default_value = "127.0.0.1:1684")]
+    to: SocketAddr,
+    /// Number of boards
+    #[arg(long, default_value_t = 1)]
+    boards: u32,
+    /// Samples per second on every channel
+    #[arg(long, default_value_t = 5.0)]
+    rate_hz: f64,
+    /// Fraction of packets to drop, 0 to 1
+    #[arg(long, default_value_t = 0.0)]
+    drop: f64,
+    /// Fraction of packets to hold back and send after the next one, 0 to 1
+    #[arg(long, default_value_t = 0.0)]
+    reorder: f64,
+    /// Fraction of samples reported as an open thermocouple, 0 to 1
+    #[arg(long, default_value_t = 0.0)]
+    faults: f64,
+    /// Boot count the boards report
+    #[arg(long, default_value_t = 1)]
+    epoch: u32,
+    /// Stop after this many data packets per board
+    #[arg(long)]
+    packets: Option<u64>,
+}
+
+/// Small xorshift generator; drops and noise need no better
+struct Random(u64);
+
+impl Random {
+    fn unit(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct Board {
+    source: PacketSource,
+    sequence: u32,
+    held: Option<Vec<u8>>,
+}
+
+pub fn run(args: &SimulateArgs) -> io::Result<()> {
+    if args.rate_hz.is_nan() || args.rate_hz <= 0.0 {
+        return Err(io::Error::other("--rate-hz must be positive"));
+    }
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    let mut random = Random(seed | 1);
+    let mut boards: Vec<Board> = (0..args.boards)
+        .map(|index| Board {
+            source: PacketSource {
+                board_id: FIRST_BOARD_ID + index,
+                epoch: args.epoch,
+            },
+            sequence: 0,
+            held: None,
+        })
+        .collect();
+    eprintln!(
+        "sending {} board(s) to {}, {} channels at {} Hz",
+        args.boards, args.to, CHANNEL_COUNT, args.rate_hz
+    );
+
+    let sample_period = Duration::from_secs_f64(1.0 / args.rate_hz);
+    let started = Instant::now();
+    let mut sent_status = 0;
+    for batch in 0.. {
+        if args.packets.is_some_and(|packets| batch >= packets) {
+            break;
+        }
+        let batch_start = batch * BATCH_SIZE as u64;
+        thread::sleep(
+            (sample_period * (batch_start + BATCH_SIZE as u64) as u32)
+                .saturating_sub(started.elapsed()),
+        );
+
+        let elapsed_s = started.elapsed().as_secs();
+        for (index, board) in boards.iter_mut().enumerate() {
+            let mut packet = SensorDataPacket::<CHANNEL_COUNT, BATCH_SIZE>::new();
+            for slot in 0..BATCH_SIZE as u64 {
+                let sample_index = batch_start + slot;
+                let time_s = sample_index as f64 / args.rate_hz;
+                let samples: [Option<Sample>; CHANNEL_COUNT] = core::array::from_fn(|channel| {
+                    let celsius = 25.0
+                        + 10.0 * index as f64
+                        + 5.0 * channel as f64
+                        + 2.0 * (time_s / 10.0 + channel as f64).sin()
+                        + 0.05 * (random.unit() - 0.5);
+                    let timestamp_us = (time_s * 1e6) as u64;
+                    // Faulted readings carry zero counts, as on the board
+                    Some(if random.unit() < args.faults {
+                        Sample {
+                            counts: 0,
+                            timestamp_us,
+                            quality: QualityFlags::OPEN,
+                        }
+                    } else {
+                        Sample {
+                            counts: (celsius * COUNTS_PER_DEGREE_C as f64).round() as i32,
+                            timestamp_us,
+                            quality: QualityFlags::VALID,
+                        }
+                    })
+                });
+                packet.store(&samples);
+            }
+            packet.packet_time = started.elapsed().as_millis() as u32;
+            let mut payload = [0u8; SensorDataPacket::<CHANNEL_COUNT, BATCH_SIZE>::ENCODED_LEN];
+            let length = packet
+                .encode_into(&mut payload)
+                .map_err(|error| io::Error::other(format!("{error:?}")))?;
+            send(&socket, args, &mut random, board, &payload[..length])?;
+
+            if elapsed_s > sent_status {
+                let status = StatusPacket::<CHANNEL_COUNT> {
+                    packet_tag: PACKET_TAG_STATUS,
+                    sync_role: 0,
+                    sync_source: 0,
+                    sync_holdover: 0,
+                    reserved: 0,
+                    sync_offset_us: 0,
+                    sync_error_us: 0,
+                    sync_age_ms: u32::MAX,
+                    overruns: 0,
+                    max_jitter_us: 0,
+                    send_errors: 0,
+                    send_timeouts: 0,
+                    queue_drops: 0,
+                    calibrations: Default::default(),
+                    packet_time: packet.packet_time,
+                };
+                let mut payload = [0u8; StatusPacket::<CHANNEL_COUNT>::ENCODED_LEN];
+                let length = status
+                    .encode_into(&mut payload)
+                    .map_err(|error| io::Error::other(format!("{error:?}")))?;
+                send(&socket, args, &mut random, board, &payload[..length])?;
+            }
+        }
+        sent_status = elapsed_s;
+    }
+
+    // Anything still held back goes out at the end
+    for board in &mut boards {
+        if let Some(held) = board.held.take() {
+            socket.send_to(&held, args.to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Frame a payload as the next packet of `board`, then drop, hold or send it
+fn send(
+    socket: &UdpSocket,
+    args: &SimulateArgs,
+    random: &mut Random,
+    board: &mut Board,
+    payload: &[u8],
+) -> io::Result<()> {
+    let mut frame = vec![0u8; payload.len() + 64];
+    let length = frame_packet::<CHANNEL_COUNT, BATCH_SIZE>(
+        PacketFormat::Versioned,
+        &board.source,
+        board.sequence,
+        payload,
+        &mut frame,
+    );
+    frame.truncate(length);
+    board.sequence = board.sequence.wrapping_add(1);
+
+    if random.unit() < args.drop {
+        return Ok(());
+    }
+    if board.held.is_none() && random.unit() < args.reorder {
+        board.held = Some(frame);
+        return Ok(());
+    }
+    socket.send_to(&frame, args.to)?;
+    if let Some(held) = board.held.take() {
+        socket.send_to(&held, args.to)?;
+    }
+    Ok(())
+}
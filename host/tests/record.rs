@@ -0,0 +1,192 @@
+//! `record` end to end: framed packets over UDP in, counters and sample rows out
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use thermosoft_protocol::{
+    PacketFormat, PacketSource, QualityFlags, Sample, SensorDataPacket, frame_packet,
+};
+
+const CHANNELS: usize = 2;
+const BOARD_ID: u32 = 0x1234_abcd;
+
+// Epoch and sequence number, sent in this order: a gap at 103, 102 late,
+// 102 again, then the board restarts in the same epoch and numbers from 0
+// again, skipping 2. It then comes back with a lower epoch, after which the
+// skipped packet from before that restart arrives late
+const SEQUENCES: [(u32, u32); 13] = [
+    (5, 100),
+    (5, 101),
+    (5, 103),
+    (5, 102),
+    (5, 102),
+    (5, 104),
+    (5, 0),
+    (5, 1),
+    (5, 3),
+    (2, 0),
+    (2, 1),
+    (5, 2),
+    (2, 2),
+];
+
+// Every packet except the duplicate 102 is written, in arrival order
+const WRITTEN: [(u32, u32); 12] = [
+    (5, 100),
+    (5, 101),
+    (5, 103),
+    (5, 102),
+    (5, 104),
+    (5, 0),
+    (5, 1),
+    (5, 3),
+    (2, 0),
+    (2, 1),
+    (5, 2),
+    (2, 2),
+];
+
+fn data_packet(epoch: u32, sequence: u32) -> Vec<u8> {
+    let mut packet = SensorDataPacket::<CHANNELS, 1>::new();
+    let samples: [Option<Sample>; CHANNELS] = core::array::from_fn(|channel| {
+        Some(Sample {
+            counts: sequence as i32 * 10 + channel as i32,
+            timestamp_us: sequence as u64 * 1000,
+            quality: QualityFlags::VALID,
+        })
+    });
+    packet.store(&samples);
+    let mut payload = [0u8; SensorDataPacket::<CHANNELS, 1>::ENCODED_LEN];
+    let length = packet.encode_into(&mut payload).unwrap();
+
+    let source = PacketSource {
+        board_id: BOARD_ID,
+        epoch,
+    };
+    let mut frame = vec![0u8; length + 64];
+    let length = frame_packet::<CHANNELS, 1>(
+        PacketFormat::Versioned,
+        &source,
+        sequence,
+        &payload[..length],
+        &mut frame,
+    );
+    frame.truncate(length);
+    frame
+}
+
+/// Run `record` on a free port, send every packet of `SEQUENCES` and return
+/// its log and the one file it wrote
+fn record(format: &str, out: &Path) -> (String, String) {
+    let _ = fs::remove_dir_all(out);
+    fs::create_dir_all(out).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_thermosoft-host"))
+        .args(["record", "--listen", "127.0.0.1:0", "--format", format])
+        .args(["--report-secs", "0", "--duration-secs", "2", "--out"])
+        .arg(out)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let listening: SocketAddr = line
+        .trim()
+        .strip_prefix("listening on ")
+        .expect("listening address")
+        .parse()
+        .unwrap();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    for (epoch, sequence) in SEQUENCES {
+        socket
+            .send_to(&data_packet(epoch, sequence), listening)
+            .unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(child.wait().unwrap().success());
+    let mut log = String::new();
+    stderr.read_to_string(&mut log).unwrap();
+
+    let files: Vec<_> = fs::read_dir(out)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1, "{files:?}");
+    let contents = fs::read_to_string(&files[0]).unwrap();
+    fs::remove_dir_all(out).unwrap();
+    (log, contents)
+}
+
+fn assert_counters(log: &str) {
+    let expected = format!(
+        "board {BOARD_ID:08x} epoch 2: 13 received, 0 lost, 1 reordered, 1 duplicate, 2 restarts"
+    );
+    assert!(log.lines().any(|line| line == expected), "{log}");
+}
+
+#[test]
+fn csv_rows_and_counters() {
+    let out = std::env::temp_dir().join(format!("thermosoft-record-csv-{}", std::process::id()));
+    let (log, contents) = record("csv", &out);
+    assert_counters(&log);
+    assert!(
+        log.contains("#2 of epoch 5 arrived after the restart"),
+        "{log}"
+    );
+
+    let mut lines = contents.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("host_time,board_id,epoch,sequence")
+    );
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), WRITTEN.len() * CHANNELS);
+    for (row, ((epoch, sequence), channel)) in rows.iter().zip(
+        WRITTEN
+            .iter()
+            .flat_map(|&packet| (0..CHANNELS).map(move |channel| (packet, channel))),
+    ) {
+        assert_eq!(row[1], format!("{BOARD_ID:08x}"));
+        assert_eq!(row[2], epoch.to_string());
+        assert_eq!(row[3], sequence.to_string());
+        assert_eq!(row[4], channel.to_string());
+        assert_eq!(row[6], (sequence * 10 + channel as u32).to_string());
+        assert_eq!(row[9], "1");
+    }
+}
+
+#[test]
+fn jsonl_rows_and_counters() {
+    let out = std::env::temp_dir().join(format!("thermosoft-record-jsonl-{}", std::process::id()));
+    let (log, contents) = record("jsonl", &out);
+    assert_counters(&log);
+
+    let rows: Vec<&str> = contents.lines().collect();
+    assert_eq!(rows.len(), WRITTEN.len() * CHANNELS);
+    for (row, ((epoch, sequence), channel)) in rows.iter().zip(
+        WRITTEN
+            .iter()
+            .flat_map(|&packet| (0..CHANNELS).map(move |channel| (packet, channel))),
+    ) {
+        assert!(
+            row.contains(&format!("\"board_id\":\"{BOARD_ID:08x}\"")),
+            "{row}"
+        );
+        assert!(row.contains(&format!("\"epoch\":{epoch},")), "{row}");
+        assert!(row.contains(&format!("\"sequence\":{sequence},")), "{row}");
+        assert!(row.contains(&format!("\"channel\":{channel},")), "{row}");
+        let counts = sequence * 10 + channel as u32;
+        assert!(row.contains(&format!("\"counts\":{counts},")), "{row}");
+        assert!(row.contains("\"valid\":true"), "{row}");
+    }
+}
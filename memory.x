@@ -0,0 +1,10 @@
+/* STM32H563RI: 2 MiB flash, 640 KiB SRAM */
+MEMORY
+{
+    /* The last two 8 KiB sectors hold the boot counter (0x081FC000) and the
+       calibration (0x081FE000), so code must stop short of them. Keep in
+       step with BOOT_COUNT_FLASH_OFFSET and CALIBRATION_FLASH_OFFSET in
+       src/lib.rs */
+    FLASH : ORIGIN = 0x08000000, LENGTH = 2048K - 16K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 640K /* SRAM1 + SRAM2 + SRAM3 */
+}
@@ -0,0 +1,58 @@
+//! CRC-32 (IEEE 802.3, as used by zlib and Ethernet)
+//!
+//! Bitwise rather than table-driven; the data checked is small and this
+//! keeps 1 KiB of table out of flash.
+
+/// Reflected CRC-32 polynomial
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// CRC-32 of `data`
+pub const fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    !crc
+}
+
+/// Known-answer vectors shared with host decoders, which should check their
+/// own CRC against the same list
+pub const CRC32_TEST_VECTORS: &[(&[u8], u32)] = &[
+    (b"", 0x0000_0000),
+    (b"a", 0xE8B7_BE43),
+    // Standard check value
+    (b"123456789", 0xCBF4_3926),
+    (b"The quick brown fox jumps over the lazy dog", 0x414F_A339),
+    (&[0xFF; 32], 0xFF6C_AB0B),
+    // Fixed 20-byte fixture shaped like a packet header (version 4, status
+    // payload, 4 channels, batch of 10, board 0x12345678, epoch 1, sequence
+    // 42). It does not follow PROTOCOL_VERSION; leave it as it is when the
+    // version changes
+    (
+        &[
+            0x54, 0x53, 0x50, 0x4B, 0x04, 0x03, 0x04, 0x0A, 0x78, 0x56, 0x34, 0x12, 0x01, 0x00,
+            0x00, 0x00, 0x2A, 0x00, 0x00, 0x00,
+        ],
+        0xA101_5F25,
+    ),
+];
+
+const _: () = {
+    let mut i = 0;
+    while i < CRC32_TEST_VECTORS.len() {
+        let (data, expected) = CRC32_TEST_VECTORS[i];
+        assert!(crc32(data) == expected, "CRC-32 test vector failed");
+        i += 1;
+    }
+};
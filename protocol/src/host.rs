@@ -0,0 +1,246 @@
+//! Decoding of versioned packets from any board, for host tools
+//!
+//! Payload sizes are taken from each packet's header rather than from
+//! build-time constants, so one receiver handles boards with different
+//! channel counts and batch sizes.
+
+use core::fmt;
+
+use crate::packet::{CalibrationInfo, ChannelSummary, EventPacket, Sample};
+use crate::quality::QualityFlags;
+use crate::wire::{Decoder, PacketError};
+use crate::{
+    COUNTS_PER_DEGREE_C, PACKET_TAG_DATA, PACKET_TAG_EVENT, PACKET_TAG_STATUS, PACKET_TAG_SUMMARY,
+    PROTOCOL_VERSION, PacketHeader, verify_packet,
+};
+
+/// Temperature of `counts`, in degrees Celsius
+pub fn counts_to_celsius(counts: i32) -> f64 {
+    counts as f64 / COUNTS_PER_DEGREE_C as f64
+}
+
+impl Sample {
+    /// Temperature in degrees Celsius
+    pub fn celsius(&self) -> f64 {
+        counts_to_celsius(self.counts)
+    }
+
+    /// True if the counts are a real measurement that can be used
+    pub fn is_valid(&self) -> bool {
+        self.quality.contains(QualityFlags::VALID)
+    }
+}
+
+impl ChannelSummary {
+    pub fn min_celsius(&self) -> f64 {
+        counts_to_celsius(self.min)
+    }
+
+    pub fn max_celsius(&self) -> f64 {
+        counts_to_celsius(self.max)
+    }
+
+    pub fn mean_celsius(&self) -> f64 {
+        counts_to_celsius(self.mean)
+    }
+
+    pub fn stddev_celsius(&self) -> f64 {
+        self.stddev as f64 / COUNTS_PER_DEGREE_C as f64
+    }
+}
+
+/// Why a received datagram could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// Too short for a header, or no packet magic
+    NoHeader,
+    /// The CRC trailer does not match
+    BadCrc,
+    /// Sent with a protocol version this crate does not decode
+    UnsupportedVersion(u8),
+    /// Payload type this crate does not know
+    UnknownPayload(u8),
+    /// The payload does not fit the header's channel count and batch size
+    Payload(PacketError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoHeader => write!(f, "no packet header"),
+            ParseError::BadCrc => write!(f, "CRC mismatch"),
+            ParseError::UnsupportedVersion(version) => {
+                write!(f, "unsupported protocol version {version}")
+            }
+            ParseError::UnknownPayload(kind) => write!(f, "unknown payload type {kind}"),
+            ParseError::Payload(error) => write!(f, "malformed payload: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<PacketError> for ParseError {
+    fn from(error: PacketError) -> Self {
+        ParseError::Payload(error)
+    }
+}
+
+/// A received versioned packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub header: PacketHeader,
+    pub payload: Payload,
+}
+
+/// Decoded payload of a versioned packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    Data(DataPayload),
+    Summary(SummaryPayload),
+    Event(EventPacket),
+    Status(StatusPayload),
+}
+
+impl Packet {
+    /// Check and decode one datagram
+    pub fn parse(bytes: &[u8]) -> Result<Self, ParseError> {
+        let header = PacketHeader::from_bytes(bytes).ok_or(ParseError::NoHeader)?;
+        let data = verify_packet(bytes).ok_or(ParseError::BadCrc)?;
+        if header.version != PROTOCOL_VERSION {
+            return Err(ParseError::UnsupportedVersion(header.version));
+        }
+        let body = &data[PacketHeader::LEN..];
+        let channels = header.channel_count as usize;
+        let batch = header.batch_size as usize;
+        let payload = match header.payload_type as u32 {
+            PACKET_TAG_DATA => Payload::Data(DataPayload::decode(body, channels, batch)?),
+            PACKET_TAG_SUMMARY => Payload::Summary(SummaryPayload::decode(body, channels)?),
+            PACKET_TAG_EVENT => {
+                let mut input = Decoder::untagged(body, EventPacket::ENCODED_LEN - 4)?;
+                Payload::Event(EventPacket::decode_body(&mut input))
+            }
+            PACKET_TAG_STATUS => Payload::Status(StatusPayload::decode(body, channels)?),
+            other => return Err(ParseError::UnknownPayload(other as u8)),
+        };
+        Ok(Self { header, payload })
+    }
+}
+
+/// Batched samples, stored channel-major like `SensorDataPacket`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPayload {
+    pub batch_size: usize,
+    /// Valid samples per channel
+    pub sample_counts: Vec<u8>,
+    pub tc_temps: Vec<i32>,
+    pub sample_times_us: Vec<u64>,
+    pub sample_quality: Vec<u16>,
+    pub packet_time: u32,
+}
+
+impl DataPayload {
+    fn decode(body: &[u8], channels: usize, batch: usize) -> Result<Self, PacketError> {
+        let slots = channels * batch;
+        let mut input = Decoder::untagged(body, channels + slots * (4 + 8 + 2) + 4)?;
+        Ok(Self {
+            batch_size: batch,
+            sample_counts: (0..channels).map(|_| input.u8()).collect(),
+            tc_temps: (0..slots).map(|_| input.i32()).collect(),
+            sample_times_us: (0..slots).map(|_| input.u64()).collect(),
+            sample_quality: (0..slots).map(|_| input.u16()).collect(),
+            packet_time: input.u32(),
+        })
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.sample_counts.len()
+    }
+
+    /// The valid samples of one channel, oldest first
+    pub fn channel(&self, channel: usize) -> impl Iterator<Item = Sample> + '_ {
+        let start = channel * self.batch_size;
+        let count = (self.sample_counts[channel] as usize).min(self.batch_size);
+        (start..start + count).map(|index| Sample {
+            counts: self.tc_temps[index],
+            timestamp_us: self.sample_times_us[index],
+            quality: QualityFlags(self.sample_quality[index]),
+        })
+    }
+
+    /// Every sample with its channel index, channel by channel
+    pub fn samples(&self) -> impl Iterator<Item = (usize, Sample)> + '_ {
+        (0..self.channel_count())
+            .flat_map(|channel| self.channel(channel).map(move |sample| (channel, sample)))
+    }
+}
+
+/// Per-channel statistics over a summary window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryPayload {
+    pub window_start_us: u64,
+    pub window_end_us: u64,
+    pub channels: Vec<ChannelSummary>,
+    pub packet_time: u32,
+}
+
+impl SummaryPayload {
+    fn decode(body: &[u8], channels: usize) -> Result<Self, PacketError> {
+        let len = 8 + 8 + channels * ChannelSummary::ENCODED_LEN + 4;
+        let mut input = Decoder::untagged(body, len)?;
+        Ok(Self {
+            window_start_us: input.u64(),
+            window_end_us: input.u64(),
+            channels: (0..channels)
+                .map(|_| ChannelSummary::decode(&mut input))
+                .collect(),
+            packet_time: input.u32(),
+        })
+    }
+}
+
+/// Board telemetry; fields as in `StatusPacket`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusPayload {
+    pub sync_role: u8,
+    pub sync_source: u8,
+    pub sync_holdover: u8,
+    pub sync_offset_us: i64,
+    pub sync_error_us: i32,
+    pub sync_age_ms: u32,
+    pub overruns: u32,
+    pub max_jitter_us: u32,
+    pub send_errors: u32,
+    pub send_timeouts: u32,
+    pub queue_drops: u32,
+    pub calibrations: Vec<CalibrationInfo>,
+    pub packet_time: u32,
+}
+
+impl StatusPayload {
+    fn decode(body: &[u8], channels: usize) -> Result<Self, PacketError> {
+        let len = 40 + channels * CalibrationInfo::ENCODED_LEN + 4;
+        let mut input = Decoder::untagged(body, len)?;
+        let sync_role = input.u8();
+        let sync_source = input.u8();
+        let sync_holdover = input.u8();
+        let _reserved = input.u8();
+        Ok(Self {
+            sync_role,
+            sync_source,
+            sync_holdover,
+            sync_offset_us: input.i64(),
+            sync_error_us: input.i32(),
+            sync_age_ms: input.u32(),
+            overruns: input.u32(),
+            max_jitter_us: input.u32(),
+            send_errors: input.u32(),
+            send_timeouts: input.u32(),
+            queue_drops: input.u32(),
+            calibrations: (0..channels)
+                .map(|_| CalibrationInfo::decode(&mut input))
+                .collect(),
+            packet_time: input.u32(),
+        })
+    }
+}
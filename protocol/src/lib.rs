@@ -0,0 +1,194 @@
+//! Wire protocol of the ThermoSoft data acquisition board
+//!
+//! Packet definitions, framing, encoding and decoding shared by the firmware
+//! and host tools. The crate is `no_std`; the `std` feature adds a decoder
+//! for packets from any board, iterators over samples and conversion to °C.
+//!
+//! Packets are generic over the channel count `N` and batch size `B` of the
+//! board that sends them. The firmware fixes both at build time; host tools
+//! read them from each packet's header.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(unsafe_code)]
+
+pub mod crc;
+#[cfg(feature = "std")]
+pub mod host;
+pub mod packet;
+pub mod quality;
+pub mod wire;
+
+pub use packet::{
+    CalibrationInfo, ChannelSummary, EventPacket, Sample, SensorDataPacket, StatusPacket,
+    SummaryPacket,
+};
+pub use quality::QualityFlags;
+pub use wire::PacketError;
+
+// Packet identifiers
+pub const PACKET_TAG_DATA: u32 = 0;
+pub const PACKET_TAG_SUMMARY: u32 = 1;
+pub const PACKET_TAG_EVENT: u32 = 2;
+pub const PACKET_TAG_STATUS: u32 = 3;
+pub const PACKET_TAG_SYNC: u32 = 4;
+
+// Start of every versioned packet
+pub const PACKET_MAGIC: [u8; 4] = *b"TSPK";
+
+// Incremented whenever the header or a payload layout changes
+pub const PROTOCOL_VERSION: u8 = 4;
+
+// CRC-32 trailer closing every versioned packet
+pub const PACKET_CRC_LEN: usize = 4;
+
+// Event kinds carried in EventPacket
+pub const EVENT_ALARM_RAISED: u8 = 1;
+pub const EVENT_ALARM_CLEARED: u8 = 2;
+pub const EVENT_INTERLOCK_TRIPPED: u8 = 3;
+pub const EVENT_INTERLOCK_RELEASED: u8 = 4;
+pub const EVENT_TRIGGER_ASSERTED: u8 = 5;
+pub const EVENT_TRIGGER_RELEASED: u8 = 6;
+
+/// Counts per degree Celsius of every temperature in a packet
+pub const COUNTS_PER_DEGREE_C: i32 = 128;
+
+/// Layout of packets sent to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketFormat {
+    /// Payload only, starting with its `u32` packet tag. Data packets carry
+    /// only the temperatures and packet time, laid out as older firmware sent them
+    Legacy,
+    /// `PacketHeader`, the payload without its packet tag, then a CRC-32 of both
+    Versioned,
+}
+
+/// Identifies the protocol, sender and payload of a versioned packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketHeader {
+    pub version: u8,
+    /// Packet tag of the payload (`PACKET_TAG_*`)
+    pub payload_type: u8,
+    pub channel_count: u8,
+    pub batch_size: u8,
+    pub board_id: u32,
+    /// Boot count of the sender, 0 if unknown
+    pub epoch: u32,
+    /// Incremented with every packet sent since boot, of any type
+    pub sequence: u32,
+}
+
+impl PacketHeader {
+    /// Encoded length in bytes
+    pub const LEN: usize = 20;
+
+    /// Header for a payload from a board with `N` channels and batches of `B`
+    pub const fn new<const N: usize, const B: usize>(
+        source: &PacketSource,
+        sequence: u32,
+        payload_tag: u32,
+    ) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            payload_type: payload_tag as u8,
+            channel_count: N as u8,
+            batch_size: B as u8,
+            board_id: source.board_id,
+            epoch: source.epoch,
+            sequence,
+        }
+    }
+
+    /// Little-endian wire format: magic, version, payload type, channel
+    /// count, batch size, board ID, epoch, sequence
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[0..4].copy_from_slice(&PACKET_MAGIC);
+        bytes[4] = self.version;
+        bytes[5] = self.payload_type;
+        bytes[6] = self.channel_count;
+        bytes[7] = self.batch_size;
+        bytes[8..12].copy_from_slice(&self.board_id.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.epoch.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes
+    }
+
+    /// Parse the start of a received packet, rejecting anything without the magic
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::LEN || bytes[0..4] != PACKET_MAGIC {
+            return None;
+        }
+        Some(Self {
+            version: bytes[4],
+            payload_type: bytes[5],
+            channel_count: bytes[6],
+            batch_size: bytes[7],
+            board_id: u32::from_le_bytes(bytes[8..12].try_into().ok()?),
+            epoch: u32::from_le_bytes(bytes[12..16].try_into().ok()?),
+            sequence: u32::from_le_bytes(bytes[16..20].try_into().ok()?),
+        })
+    }
+}
+
+/// Identity of a board run, fixed after startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketSource {
+    pub board_id: u32,
+    /// Boot count, 0 if it could not be stored
+    pub epoch: u32,
+}
+
+/// Write a tagged packet into `buffer` in the given format, returning its length
+/// `packet` is a payload from `encode_into`, packet tag first, from a board
+/// with `N` channels and batches of `B`
+pub fn frame_packet<const N: usize, const B: usize>(
+    format: PacketFormat,
+    source: &PacketSource,
+    sequence: u32,
+    packet: &[u8],
+    buffer: &mut [u8],
+) -> usize {
+    match format {
+        PacketFormat::Legacy => {
+            // Older receivers expect data packets as the packet tag, the
+            // temperatures channel by channel and the packet time, nothing else
+            let tag = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+            if tag == PACKET_TAG_DATA {
+                let temps_at = SensorDataPacket::<N, B>::TEMPS_OFFSET;
+                let temps = &packet[temps_at..temps_at + SensorDataPacket::<N, B>::TEMPS_LEN];
+                let time_at = SensorDataPacket::<N, B>::ENCODED_LEN - 4;
+                let time_to = 4 + temps.len();
+                buffer[..4].copy_from_slice(&packet[..4]);
+                buffer[4..time_to].copy_from_slice(temps);
+                buffer[time_to..time_to + 4].copy_from_slice(&packet[time_at..time_at + 4]);
+                SensorDataPacket::<N, B>::LEGACY_LEN
+            } else {
+                buffer[..packet.len()].copy_from_slice(packet);
+                packet.len()
+            }
+        }
+        PacketFormat::Versioned => {
+            let tag = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+            let body = &packet[4..];
+            buffer[..PacketHeader::LEN]
+                .copy_from_slice(&PacketHeader::new::<N, B>(source, sequence, tag).to_bytes());
+            let crc_at = PacketHeader::LEN + body.len();
+            buffer[PacketHeader::LEN..crc_at].copy_from_slice(body);
+            let crc = crc::crc32(&buffer[..crc_at]);
+            buffer[crc_at..crc_at + PACKET_CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+            crc_at + PACKET_CRC_LEN
+        }
+    }
+}
+
+/// Check the CRC trailer of a received versioned packet
+/// Returns the header and payload without the trailer, or `None` if corrupt
+pub fn verify_packet(packet: &[u8]) -> Option<&[u8]> {
+    let crc_at = packet.len().checked_sub(PACKET_CRC_LEN)?;
+    let (data, trailer) = packet.split_at(crc_at);
+    let crc = u32::from_le_bytes(trailer.try_into().ok()?);
+    (crc::crc32(data) == crc).then_some(data)
+}
@@ -0,0 +1,595 @@
+//! Payloads for a board with `N` channels and batches of `B` samples
+//!
+//! Each payload is encoded field by field in declaration order,
+//! little-endian, with no padding, starting with its `u32` packet tag.
+
+use crate::quality::QualityFlags;
+use crate::wire::{Decoder, Encoder, PacketError};
+use crate::{
+    EVENT_TRIGGER_ASSERTED, EVENT_TRIGGER_RELEASED, PACKET_TAG_DATA, PACKET_TAG_EVENT,
+    PACKET_TAG_STATUS, PACKET_TAG_SUMMARY,
+};
+
+/// One sample of one channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sample {
+    /// Temperature in counts of 1/128°C
+    pub counts: i32,
+    /// Read time, in microseconds since boot or on the shared timebase
+    pub timestamp_us: u64,
+    pub quality: QualityFlags,
+}
+
+/// Batched sensor data packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorDataPacket<const N: usize, const B: usize> {
+    pub packet_tag: u32,                // Packet identifier
+    pub sample_counts: [u8; N],         // Valid samples per thermocouple in this batch
+    pub tc_temps: [[i32; B]; N],        // Temperature batch per thermocouple
+    pub sample_times_us: [[u64; B]; N], // Read time of each sample (microseconds since boot)
+    pub sample_quality: [[u16; B]; N],  // QualityFlags bits of each sample
+    pub packet_time: u32,               // Timestamp when packet was sent (milliseconds)
+}
+
+impl<const N: usize, const B: usize> Default for SensorDataPacket<N, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const B: usize> SensorDataPacket<N, B> {
+    /// Offset of `tc_temps` in the encoded packet
+    pub const TEMPS_OFFSET: usize = 4 + N;
+    /// Length of `tc_temps` in the encoded packet
+    pub const TEMPS_LEN: usize = N * B * 4;
+    /// Encoded length in bytes
+    pub const ENCODED_LEN: usize = Self::TEMPS_OFFSET + Self::TEMPS_LEN + N * B * (8 + 2) + 4;
+    /// Length in the legacy format: packet tag, `tc_temps` and packet time
+    pub const LEGACY_LEN: usize = 4 + Self::TEMPS_LEN + 4;
+
+    /// Create a new empty packet
+    pub const fn new() -> Self {
+        Self {
+            packet_tag: PACKET_TAG_DATA,
+            sample_counts: [0; N],
+            tc_temps: [[0; B]; N],
+            sample_times_us: [[0; B]; N],
+            sample_quality: [[0; B]; N],
+            packet_time: 0,
+        }
+    }
+
+    /// Append each channel's sample, if any, to that channel's batch
+    /// Channels sampled at different rates fill their batches at different speeds
+    pub fn store<S: Copy + Into<Sample>>(&mut self, samples: &[Option<S>; N]) {
+        for (channel, sample) in samples.iter().enumerate() {
+            let Some(sample) = sample else {
+                continue;
+            };
+            let sample: Sample = (*sample).into();
+            let index = self.sample_counts[channel] as usize;
+            if index < B {
+                self.tc_temps[channel][index] = sample.counts;
+                self.sample_times_us[channel][index] = sample.timestamp_us;
+                self.sample_quality[channel][index] = sample.quality.bits();
+                self.sample_counts[channel] += 1;
+            }
+        }
+    }
+
+    /// True if no channel has any samples yet
+    pub fn is_empty(&self) -> bool {
+        self.sample_counts.iter().all(|&count| count == 0)
+    }
+
+    /// True once any channel has a full batch
+    pub fn is_full(&self) -> bool {
+        self.sample_counts.iter().any(|&count| count as usize >= B)
+    }
+
+    /// Write the packet into `buffer`, returning its length
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, PacketError> {
+        let mut out = Encoder::new(buffer, Self::ENCODED_LEN)?;
+        out.u32(self.packet_tag);
+        out.bytes(&self.sample_counts);
+        self.tc_temps
+            .as_flattened()
+            .iter()
+            .for_each(|&t| out.i32(t));
+        self.sample_times_us
+            .as_flattened()
+            .iter()
+            .for_each(|&t| out.u64(t));
+        self.sample_quality
+            .as_flattened()
+            .iter()
+            .for_each(|&q| out.u16(q));
+        out.u32(self.packet_time);
+        Ok(out.len())
+    }
+
+    /// Read a packet written by `encode_into`
+    pub fn decode(bytes: &[u8]) -> Result<Self, PacketError> {
+        let mut input = Decoder::new(bytes, Self::ENCODED_LEN, PACKET_TAG_DATA)?;
+        let mut packet = Self::new();
+        packet
+            .sample_counts
+            .iter_mut()
+            .for_each(|c| *c = input.u8());
+        packet
+            .tc_temps
+            .as_flattened_mut()
+            .iter_mut()
+            .for_each(|t| *t = input.i32());
+        packet
+            .sample_times_us
+            .as_flattened_mut()
+            .iter_mut()
+            .for_each(|t| *t = input.u64());
+        packet
+            .sample_quality
+            .as_flattened_mut()
+            .iter_mut()
+            .for_each(|q| *q = input.u16());
+        packet.packet_time = input.u32();
+        Ok(packet)
+    }
+}
+
+/// Statistics of one channel over a summary window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelSummary {
+    pub count: u32,  // Valid samples in the window
+    pub min: i32,    // Minimum (counts)
+    pub max: i32,    // Maximum (counts)
+    pub mean: i32,   // Mean (counts)
+    pub stddev: u32, // Population standard deviation (counts)
+}
+
+impl ChannelSummary {
+    pub(crate) const ENCODED_LEN: usize = 20;
+
+    fn encode(&self, out: &mut Encoder) {
+        out.u32(self.count);
+        out.i32(self.min);
+        out.i32(self.max);
+        out.i32(self.mean);
+        out.u32(self.stddev);
+    }
+
+    pub(crate) fn decode(input: &mut Decoder) -> Self {
+        Self {
+            count: input.u32(),
+            min: input.i32(),
+            max: input.i32(),
+            mean: input.i32(),
+            stddev: input.u32(),
+        }
+    }
+}
+
+/// Per-channel summary statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SummaryPacket<const N: usize> {
+    pub packet_tag: u32,               // Packet identifier (PACKET_TAG_SUMMARY)
+    pub window_start_us: u64,          // First sample in the window (microseconds since boot)
+    pub window_end_us: u64,            // End of the window (microseconds since boot)
+    pub channels: [ChannelSummary; N], // Statistics per thermocouple
+    pub packet_time: u32,              // Timestamp when packet was sent (milliseconds)
+}
+
+impl<const N: usize> SummaryPacket<N> {
+    /// Encoded length in bytes
+    pub const ENCODED_LEN: usize = 4 + 8 + 8 + N * ChannelSummary::ENCODED_LEN + 4;
+
+    pub fn new(window_start_us: u64, window_end_us: u64, channels: [ChannelSummary; N]) -> Self {
+        Self {
+            packet_tag: PACKET_TAG_SUMMARY,
+            window_start_us,
+            window_end_us,
+            channels,
+            packet_time: 0,
+        }
+    }
+
+    /// Write the packet into `buffer`, returning its length
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, PacketError> {
+        let mut out = Encoder::new(buffer, Self::ENCODED_LEN)?;
+        out.u32(self.packet_tag);
+        out.u64(self.window_start_us);
+        out.u64(self.window_end_us);
+        self.channels.iter().for_each(|c| c.encode(&mut out));
+        out.u32(self.packet_time);
+        Ok(out.len())
+    }
+
+    /// Read a packet written by `encode_into`
+    pub fn decode(bytes: &[u8]) -> Result<Self, PacketError> {
+        let mut input = Decoder::new(bytes, Self::ENCODED_LEN, PACKET_TAG_SUMMARY)?;
+        Ok(Self {
+            packet_tag: PACKET_TAG_SUMMARY,
+            window_start_us: input.u64(),
+            window_end_us: input.u64(),
+            channels: core::array::from_fn(|_| ChannelSummary::decode(&mut input)),
+            packet_time: input.u32(),
+        })
+    }
+}
+
+/// Immediate event, sent outside the batch stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventPacket {
+    pub packet_tag: u32,   // Packet identifier (PACKET_TAG_EVENT)
+    pub event_kind: u8,    // EVENT_* kind
+    pub source_id: u8,     // Alarm rule id, interlock output index, or trigger input (0)
+    pub channel: u8,       // Channel index (0-based), 0xFF if not channel specific
+    pub reserved: u8,      // Padding, always zero
+    pub value: i32, // Alarm: watched value (counts, or counts/s for rate rules); interlock: 1 if tripped by a fault
+    pub timestamp_us: u64, // Time of the event (microseconds since boot)
+    pub packet_time: u32, // Timestamp when packet was sent (milliseconds)
+}
+
+impl EventPacket {
+    /// Encoded length in bytes
+    pub const ENCODED_LEN: usize = 24;
+
+    /// Event of `event_kind` (`EVENT_*`) at `timestamp_us`
+    pub fn new(event_kind: u8, source_id: u8, channel: u8, value: i32, timestamp_us: u64) -> Self {
+        Self {
+            packet_tag: PACKET_TAG_EVENT,
+            event_kind,
+            source_id,
+            channel,
+            reserved: 0,
+            value,
+            timestamp_us,
+            packet_time: 0,
+        }
+    }
+
+    /// Marker for a trigger input edge at `timestamp_us`
+    pub fn trigger(active: bool, timestamp_us: u64) -> Self {
+        let event_kind = if active {
+            EVENT_TRIGGER_ASSERTED
+        } else {
+            EVENT_TRIGGER_RELEASED
+        };
+        Self::new(event_kind, 0, 0xFF, 0, timestamp_us)
+    }
+
+    /// Write the packet into `buffer`, returning its length
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, PacketError> {
+        let mut out = Encoder::new(buffer, Self::ENCODED_LEN)?;
+        out.u32(self.packet_tag);
+        out.u8(self.event_kind);
+        out.u8(self.source_id);
+        out.u8(self.channel);
+        out.u8(self.reserved);
+        out.i32(self.value);
+        out.u64(self.timestamp_us);
+        out.u32(self.packet_time);
+        Ok(out.len())
+    }
+
+    /// Read a packet written by `encode_into`
+    pub fn decode(bytes: &[u8]) -> Result<Self, PacketError> {
+        let mut input = Decoder::new(bytes, Self::ENCODED_LEN, PACKET_TAG_EVENT)?;
+        Ok(Self::decode_body(&mut input))
+    }
+
+    /// Read the fields after the packet tag
+    pub(crate) fn decode_body(input: &mut Decoder) -> Self {
+        Self {
+            packet_tag: PACKET_TAG_EVENT,
+            event_kind: input.u8(),
+            source_id: input.u8(),
+            channel: input.u8(),
+            reserved: input.u8(),
+            value: input.i32(),
+            timestamp_us: input.u64(),
+            packet_time: input.u32(),
+        }
+    }
+}
+
+/// Periodic board telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusPacket<const N: usize> {
+    pub packet_tag: u32,                    // Packet identifier (PACKET_TAG_STATUS)
+    pub sync_role: u8,                      // 0 = standalone, 1 = master, 2 = follower
+    pub sync_source: u8,                    // SyncSource of the last clock correction
+    pub sync_holdover: u8,                  // 1 if a follower has lost its sync source
+    pub reserved: u8,                       // Padding, always zero
+    pub sync_offset_us: i64,                // Shared time minus local boot time (microseconds)
+    pub sync_error_us: i32, // Clock error found at the last correction (microseconds)
+    pub sync_age_ms: u32,   // Time since the last correction, u32::MAX if never
+    pub overruns: u32,      // Sample instants skipped since boot
+    pub max_jitter_us: u32, // Worst sample wake-up lateness since boot
+    pub send_errors: u32,   // UDP sends that failed since boot
+    pub send_timeouts: u32, // UDP sends that timed out since boot
+    pub queue_drops: u32,   // Packets dropped on a full send queue since boot
+    pub calibrations: [CalibrationInfo; N], // Active calibration per thermocouple
+    pub packet_time: u32,   // Timestamp when packet was sent (milliseconds)
+}
+
+/// Identity of a channel's active calibration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CalibrationInfo {
+    pub id: u32,   // Calibration run identifier, 0 if uncalibrated
+    pub date: u32, // Calibration date as YYYYMMDD, 0 if uncalibrated
+}
+
+impl CalibrationInfo {
+    pub(crate) const ENCODED_LEN: usize = 8;
+
+    pub(crate) fn decode(input: &mut Decoder) -> Self {
+        Self {
+            id: input.u32(),
+            date: input.u32(),
+        }
+    }
+}
+
+impl<const N: usize> StatusPacket<N> {
+    /// Encoded length in bytes
+    pub const ENCODED_LEN: usize = 44 + N * CalibrationInfo::ENCODED_LEN + 4;
+
+    /// Write the packet into `buffer`, returning its length
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, PacketError> {
+        let mut out = Encoder::new(buffer, Self::ENCODED_LEN)?;
+        out.u32(self.packet_tag);
+        out.u8(self.sync_role);
+        out.u8(self.sync_source);
+        out.u8(self.sync_holdover);
+        out.u8(self.reserved);
+        out.i64(self.sync_offset_us);
+        out.i32(self.sync_error_us);
+        out.u32(self.sync_age_ms);
+        out.u32(self.overruns);
+        out.u32(self.max_jitter_us);
+        out.u32(self.send_errors);
+        out.u32(self.send_timeouts);
+        out.u32(self.queue_drops);
+        for calibration in &self.calibrations {
+            out.u32(calibration.id);
+            out.u32(calibration.date);
+        }
+        out.u32(self.packet_time);
+        Ok(out.len())
+    }
+
+    /// Read a packet written by `encode_into`
+    pub fn decode(bytes: &[u8]) -> Result<Self, PacketError> {
+        let mut input = Decoder::new(bytes, Self::ENCODED_LEN, PACKET_TAG_STATUS)?;
+        Ok(Self {
+            packet_tag: PACKET_TAG_STATUS,
+            sync_role: input.u8(),
+            sync_source: input.u8(),
+            sync_holdover: input.u8(),
+            reserved: input.u8(),
+            sync_offset_us: input.i64(),
+            sync_error_us: input.i32(),
+            sync_age_ms: input.u32(),
+            overruns: input.u32(),
+            max_jitter_us: input.u32(),
+            send_errors: input.u32(),
+            send_timeouts: input.u32(),
+            queue_drops: input.u32(),
+            calibrations: core::array::from_fn(|_| CalibrationInfo::decode(&mut input)),
+            packet_time: input.u32(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Every payload must survive an encode/decode round trip, and encode to
+    //! the bytes the firmware sent when packets were `repr(C, packed)`
+    //! structs: fields in declaration order, little-endian, no padding.
+
+    use super::*;
+
+    const CHANNELS: usize = 2;
+    const BATCH: usize = 2;
+
+    fn encode<const LEN: usize>(
+        encode_into: impl FnOnce(&mut [u8]) -> Result<usize, PacketError>,
+    ) -> [u8; LEN] {
+        let mut bytes = [0u8; LEN];
+        assert_eq!(encode_into(&mut bytes), Ok(LEN));
+        bytes
+    }
+
+    fn data_packet() -> SensorDataPacket<CHANNELS, BATCH> {
+        SensorDataPacket {
+            packet_tag: PACKET_TAG_DATA,
+            sample_counts: [2, 1],
+            tc_temps: [[3200, -256], [0x0102_0304, 0]],
+            sample_times_us: [[1_000_000, 1_200_000], [0x0102_0304_0506_0708, 0]],
+            sample_quality: [[0x0008, 0x0409], [0x0010, 0]],
+            packet_time: 0xDEAD_BEEF,
+        }
+    }
+
+    const DATA_BYTES: [u8; 66] = [
+        0x00, 0x00, 0x00, 0x00, // packet_tag
+        0x02, 0x01, // sample_counts
+        0x80, 0x0C, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, // tc_temps[0]
+        0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, // tc_temps[1]
+        0x40, 0x42, 0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, // sample_times_us[0][0]
+        0x80, 0x4F, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, // sample_times_us[0][1]
+        0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, // sample_times_us[1][0]
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // sample_times_us[1][1]
+        0x08, 0x00, 0x09, 0x04, 0x10, 0x00, 0x00, 0x00, // sample_quality
+        0xEF, 0xBE, 0xAD, 0xDE, // packet_time
+    ];
+
+    fn summary_packet() -> SummaryPacket<CHANNELS> {
+        let mut packet = SummaryPacket::new(
+            0x0000_0001_0000_0002,
+            0x0000_0001_0000_0003,
+            [
+                ChannelSummary {
+                    count: 50,
+                    min: -128,
+                    max: 3200,
+                    mean: 1600,
+                    stddev: 12,
+                },
+                ChannelSummary {
+                    count: 1,
+                    min: i32::MAX,
+                    max: i32::MAX,
+                    mean: i32::MAX,
+                    stddev: 0,
+                },
+            ],
+        );
+        packet.packet_time = 0x0102_0304;
+        packet
+    }
+
+    const SUMMARY_BYTES: [u8; 64] = [
+        0x01, 0x00, 0x00, 0x00, // packet_tag
+        0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // window_start_us
+        0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // window_end_us
+        0x32, 0x00, 0x00, 0x00, 0x80, 0xFF, 0xFF, 0xFF, // channels[0] count, min
+        0x80, 0x0C, 0x00, 0x00, 0x40, 0x06, 0x00, 0x00, // channels[0] max, mean
+        0x0C, 0x00, 0x00, 0x00, // channels[0] stddev
+        0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x7F, // channels[1] count, min
+        0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 0x7F, // channels[1] max, mean
+        0x00, 0x00, 0x00, 0x00, // channels[1] stddev
+        0x04, 0x03, 0x02, 0x01, // packet_time
+    ];
+
+    fn event_packet() -> EventPacket {
+        let mut packet = EventPacket::new(1, 7, 2, -25_600, 0x0000_0012_3456_789A);
+        packet.packet_time = 1000;
+        packet
+    }
+
+    const EVENT_BYTES: [u8; 24] = [
+        0x02, 0x00, 0x00, 0x00, // packet_tag
+        0x01, 0x07, 0x02, 0x00, // event_kind, source_id, channel, reserved
+        0x00, 0x9C, 0xFF, 0xFF, // value
+        0x9A, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, // timestamp_us
+        0xE8, 0x03, 0x00, 0x00, // packet_time
+    ];
+
+    fn status_packet() -> StatusPacket<CHANNELS> {
+        StatusPacket {
+            packet_tag: PACKET_TAG_STATUS,
+            sync_role: 2,
+            sync_source: 1,
+            sync_holdover: 1,
+            reserved: 0,
+            sync_offset_us: -1_000_000,
+            sync_error_us: -12,
+            sync_age_ms: u32::MAX,
+            overruns: 3,
+            max_jitter_us: 250,
+            send_errors: 4,
+            send_timeouts: 5,
+            queue_drops: 6,
+            calibrations: [
+                CalibrationInfo {
+                    id: 0x1122_3344,
+                    date: 20260115,
+                },
+                CalibrationInfo::default(),
+            ],
+            packet_time: 0x0A0B_0C0D,
+        }
+    }
+
+    const STATUS_BYTES: [u8; 64] = [
+        0x03, 0x00, 0x00, 0x00, // packet_tag
+        0x02, 0x01, 0x01, 0x00, // sync_role, sync_source, sync_holdover, reserved
+        0xC0, 0xBD, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // sync_offset_us
+        0xF4, 0xFF, 0xFF, 0xFF, // sync_error_us
+        0xFF, 0xFF, 0xFF, 0xFF, // sync_age_ms
+        0x03, 0x00, 0x00, 0x00, // overruns
+        0xFA, 0x00, 0x00, 0x00, // max_jitter_us
+        0x04, 0x00, 0x00, 0x00, // send_errors
+        0x05, 0x00, 0x00, 0x00, // send_timeouts
+        0x06, 0x00, 0x00, 0x00, // queue_drops
+        0x44, 0x33, 0x22, 0x11, 0x13, 0x25, 0x35, 0x01, // calibrations[0]
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // calibrations[1]
+        0x0D, 0x0C, 0x0B, 0x0A, // packet_time
+    ];
+
+    #[test]
+    fn data_packet_matches_packed_layout() {
+        let packet = data_packet();
+        let bytes = encode::<66>(|buffer| packet.encode_into(buffer));
+        assert_eq!(bytes, DATA_BYTES);
+        assert_eq!(SensorDataPacket::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn summary_packet_matches_packed_layout() {
+        let packet = summary_packet();
+        let bytes = encode::<64>(|buffer| packet.encode_into(buffer));
+        assert_eq!(bytes, SUMMARY_BYTES);
+        assert_eq!(SummaryPacket::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn event_packet_matches_packed_layout() {
+        let packet = event_packet();
+        let bytes = encode::<24>(|buffer| packet.encode_into(buffer));
+        assert_eq!(bytes, EVENT_BYTES);
+        assert_eq!(EventPacket::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn status_packet_matches_packed_layout() {
+        let packet = status_packet();
+        let bytes = encode::<64>(|buffer| packet.encode_into(buffer));
+        assert_eq!(bytes, STATUS_BYTES);
+        assert_eq!(StatusPacket::decode(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn default_sized_packets_round_trip() {
+        // The firmware's default layout: 4 channels, batches of 10
+        let mut data = SensorDataPacket::<4, 10>::new();
+        for slot in 0..10 {
+            data.store(&core::array::from_fn::<_, 4, _>(|channel| {
+                (channel != 3 || slot % 2 == 0).then_some(Sample {
+                    counts: (channel as i32 - 2) * 40_000 + slot,
+                    timestamp_us: u64::MAX - slot as u64,
+                    quality: QualityFlags::VALID | QualityFlags::FILTERED,
+                })
+            }));
+        }
+        data.packet_time = 123_456;
+        let bytes =
+            encode::<{ SensorDataPacket::<4, 10>::ENCODED_LEN }>(|buffer| data.encode_into(buffer));
+        assert_eq!(SensorDataPacket::decode(&bytes), Ok(data));
+
+        let summary = SummaryPacket::<4>::new(
+            1,
+            u64::MAX,
+            core::array::from_fn(|channel| ChannelSummary {
+                count: channel as u32,
+                min: i32::MIN,
+                max: i32::MAX,
+                mean: -(channel as i32),
+                stddev: u32::MAX,
+            }),
+        );
+        let bytes =
+            encode::<{ SummaryPacket::<4>::ENCODED_LEN }>(|buffer| summary.encode_into(buffer));
+        assert_eq!(SummaryPacket::decode(&bytes), Ok(summary));
+    }
+
+    #[test]
+    fn decoding_checks_tag_and_length() {
+        let mut bytes = EVENT_BYTES;
+        assert!(EventPacket::decode(&bytes[..23]).is_err());
+        bytes[0] = 3;
+        assert!(EventPacket::decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+//! Per-sample data quality flags
+
+/// Bitfield describing how a sample was obtained or altered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QualityFlags(pub u16);
+
+impl QualityFlags {
+    pub const NONE: QualityFlags = QualityFlags(0);
+    /// Sample deviated from the channel median by more than the spike threshold
+    pub const SPIKE: QualityFlags = QualityFlags(1 << 0);
+    /// Sample changed faster than the channel's maximum slew rate
+    pub const SLEW: QualityFlags = QualityFlags(1 << 1);
+    /// Sample value was replaced by the outlier filter
+    pub const REPLACED: QualityFlags = QualityFlags(1 << 2);
+    /// Sample is a real measurement: no converter fault and no bus error
+    pub const VALID: QualityFlags = QualityFlags(1 << 3);
+    /// Thermocouple open circuit
+    pub const OPEN: QualityFlags = QualityFlags(1 << 4);
+    /// Input over- or undervoltage
+    pub const OVUV: QualityFlags = QualityFlags(1 << 5);
+    /// Thermocouple temperature out of range or past a fault threshold
+    pub const TC_RANGE: QualityFlags = QualityFlags(1 << 6);
+    /// Cold-junction temperature out of range or past a fault threshold
+    pub const CJ_RANGE: QualityFlags = QualityFlags(1 << 7);
+    /// The converter could not be read
+    pub const SPI_ERROR: QualityFlags = QualityFlags(1 << 8);
+    /// Data ready timed out, so the value may be from an earlier conversion
+    pub const STALE: QualityFlags = QualityFlags(1 << 9);
+    /// Sample passed through a smoothing filter
+    pub const FILTERED: QualityFlags = QualityFlags(1 << 10);
+    /// First sample stored after a trigger input edge
+    pub const TRIGGER: QualityFlags = QualityFlags(1 << 11);
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn contains(self, other: QualityFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: QualityFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl core::ops::BitOr for QualityFlags {
+    type Output = QualityFlags;
+
+    fn bitor(self, rhs: QualityFlags) -> QualityFlags {
+        QualityFlags(self.0 | rhs.0)
+    }
+}
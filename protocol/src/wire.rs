@@ -0,0 +1,127 @@
+//! Little-endian encoding helpers for network packets
+//!
+//! Packets are written field by field rather than by viewing a packed struct
+//! as bytes, so the wire format does not depend on the target's layout or
+//! endianness and the same code decodes them on a host.
+
+/// Why a packet could not be encoded or decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketError {
+    /// The output buffer is shorter than the encoded packet
+    BufferTooSmall,
+    /// The input is not the length of the expected packet
+    WrongLength,
+    /// The packet tag does not match the expected packet
+    WrongTag,
+}
+
+/// Sequential writer into a byte buffer
+pub struct Encoder<'a> {
+    buffer: &'a mut [u8],
+    at: usize,
+}
+
+impl<'a> Encoder<'a> {
+    /// Writer for a packet of `len` bytes; fails if `buffer` is shorter
+    pub fn new(buffer: &'a mut [u8], len: usize) -> Result<Self, PacketError> {
+        if buffer.len() < len {
+            return Err(PacketError::BufferTooSmall);
+        }
+        Ok(Self { buffer, at: 0 })
+    }
+
+    /// Bytes written so far
+    pub fn len(&self) -> usize {
+        self.at
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.at == 0
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer[self.at..self.at + bytes.len()].copy_from_slice(bytes);
+        self.at += bytes.len();
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn i64(&mut self, value: i64) {
+        self.bytes(&value.to_le_bytes());
+    }
+}
+
+/// Sequential reader over a received packet
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Reader for a packet that must be exactly `len` bytes with tag `tag`
+    pub fn new(bytes: &'a [u8], len: usize, tag: u32) -> Result<Self, PacketError> {
+        let mut decoder = Self::untagged(bytes, len)?;
+        if decoder.u32() != tag {
+            return Err(PacketError::WrongTag);
+        }
+        Ok(decoder)
+    }
+
+    /// Reader for the payload of a versioned packet, which has no packet tag
+    pub fn untagged(bytes: &'a [u8], len: usize) -> Result<Self, PacketError> {
+        if bytes.len() != len {
+            return Err(PacketError::WrongLength);
+        }
+        Ok(Self { bytes, at: 0 })
+    }
+
+    fn array<const L: usize>(&mut self) -> [u8; L] {
+        let mut array = [0; L];
+        array.copy_from_slice(&self.bytes[self.at..self.at + L]);
+        self.at += L;
+        array
+    }
+
+    pub fn u8(&mut self) -> u8 {
+        self.array::<1>()[0]
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.array())
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.array())
+    }
+
+    pub fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.array())
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.array())
+    }
+
+    pub fn i64(&mut self) -> i64 {
+        i64::from_le_bytes(self.array())
+    }
+}
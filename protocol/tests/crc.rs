@@ -0,0 +1,73 @@
+//! A corrupted byte anywhere after the magic must fail the CRC check
+
+use thermosoft_protocol::host::{Packet, ParseError};
+use thermosoft_protocol::{
+    EVENT_ALARM_RAISED, EventPacket, PACKET_MAGIC, PacketFormat, PacketSource, frame_packet,
+    verify_packet,
+};
+
+fn framed_event() -> Vec<u8> {
+    let mut event = EventPacket::new(EVENT_ALARM_RAISED, 7, 2, 25_600, 1_234_567);
+    event.packet_time = 1_234;
+    let mut payload = [0u8; EventPacket::ENCODED_LEN];
+    let length = event.encode_into(&mut payload).unwrap();
+    let source = PacketSource {
+        board_id: 0x1234_5678,
+        epoch: 3,
+    };
+    let mut frame = vec![0u8; 256];
+    let length = frame_packet::<4, 10>(
+        PacketFormat::Versioned,
+        &source,
+        99,
+        &payload[..length],
+        &mut frame,
+    );
+    frame.truncate(length);
+    frame
+}
+
+#[test]
+fn intact_packet_passes() {
+    let frame = framed_event();
+    assert_eq!(verify_packet(&frame), Some(&frame[..frame.len() - 4]));
+    assert!(Packet::parse(&frame).is_ok());
+}
+
+#[test]
+fn flipped_byte_fails_crc() {
+    let frame = framed_event();
+    // Corrupting the magic leaves no header to check, see below
+    for index in PACKET_MAGIC.len()..frame.len() {
+        for mask in [0x01, 0x80, 0xFF] {
+            let mut corrupt = frame.clone();
+            corrupt[index] ^= mask;
+            assert_eq!(verify_packet(&corrupt), None, "byte {index} ^ {mask:#04x}");
+            assert_eq!(
+                Packet::parse(&corrupt).unwrap_err(),
+                ParseError::BadCrc,
+                "byte {index} ^ {mask:#04x}"
+            );
+        }
+    }
+}
+
+#[test]
+fn flipped_magic_is_not_a_packet() {
+    let frame = framed_event();
+    for index in 0..PACKET_MAGIC.len() {
+        let mut corrupt = frame.clone();
+        corrupt[index] ^= 0x01;
+        assert_eq!(verify_packet(&corrupt), None);
+        assert_eq!(Packet::parse(&corrupt).unwrap_err(), ParseError::NoHeader);
+    }
+}
+
+#[test]
+fn truncated_packet_fails_crc() {
+    let frame = framed_event();
+    assert_eq!(
+        Packet::parse(&frame[..frame.len() - 1]).unwrap_err(),
+        ParseError::BadCrc
+    );
+}
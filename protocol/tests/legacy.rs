@@ -0,0 +1,64 @@
+//! The legacy format must match what older firmware sent byte for byte
+
+use thermosoft_protocol::{
+    PACKET_TAG_DATA, PacketFormat, PacketSource, QualityFlags, Sample, SensorDataPacket,
+    frame_packet,
+};
+
+const CHANNELS: usize = 4;
+const BATCH: usize = 10;
+
+/// The original `repr(C, packed)` data packet: tag, four `[i32; 10]`
+/// temperature batches and the packet time
+fn baseline_packet(temps: &[[i32; BATCH]; CHANNELS], packet_time: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&PACKET_TAG_DATA.to_le_bytes());
+    for channel in temps {
+        for temp in channel {
+            bytes.extend_from_slice(&temp.to_le_bytes());
+        }
+    }
+    bytes.extend_from_slice(&packet_time.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn legacy_data_packet_matches_baseline_layout() {
+    // Distinct values per channel and slot, including negative ones
+    let temp = |ch: usize, slot: usize| (ch as i32 - 1) * 100_000 + slot as i32;
+    let temps: [[i32; BATCH]; CHANNELS] =
+        core::array::from_fn(|ch| core::array::from_fn(|slot| temp(ch, slot)));
+
+    let mut packet = SensorDataPacket::<CHANNELS, BATCH>::new();
+    for slot in 0..BATCH {
+        let samples: [Option<Sample>; CHANNELS] = core::array::from_fn(|ch| {
+            Some(Sample {
+                counts: temp(ch, slot),
+                timestamp_us: 1_000_000 + slot as u64,
+                quality: QualityFlags::VALID,
+            })
+        });
+        packet.store(&samples);
+    }
+    packet.packet_time = 0x1234_5678;
+
+    let mut payload = [0u8; SensorDataPacket::<CHANNELS, BATCH>::ENCODED_LEN];
+    let length = packet.encode_into(&mut payload).unwrap();
+    let mut frame = [0u8; 512];
+    let source = PacketSource {
+        board_id: 1,
+        epoch: 1,
+    };
+    let framed = frame_packet::<CHANNELS, BATCH>(
+        PacketFormat::Legacy,
+        &source,
+        0,
+        &payload[..length],
+        &mut frame,
+    );
+
+    let expected = baseline_packet(&temps, 0x1234_5678);
+    assert_eq!(expected.len(), 168);
+    assert_eq!(framed, SensorDataPacket::<CHANNELS, BATCH>::LEGACY_LEN);
+    assert_eq!(&frame[..framed], &expected[..]);
+}
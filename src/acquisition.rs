@@ -0,0 +1,190 @@
+//! Channel array acquisition
+//!
+//! A board variant is described by a single array of front ends; the
+//! engine is generic over the channel count so 4-, 8- and 16-channel boards
+//! share the same firmware.
+
+use embassy_futures::join::join_array;
+use embassy_time::{Duration, Instant, with_timeout};
+
+use crate::frontend::ThermocoupleFrontend;
+use crate::max31856::FaultStatus;
+use crate::max31856::timing::ConversionMode;
+use crate::quality::{self, QualityFlags};
+
+/// How channels are sampled relative to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AcquisitionMode {
+    /// Read free-running converters one after another
+    Sequential,
+    /// Trigger one-shot conversions on all channels back-to-back and read
+    /// them once every channel reports data ready, giving time-aligned samples
+    Simultaneous,
+}
+
+impl AcquisitionMode {
+    /// Converter mode required by this acquisition mode
+    pub const fn conversion_mode(self) -> ConversionMode {
+        match self {
+            AcquisitionMode::Sequential => ConversionMode::Continuous,
+            AcquisitionMode::Simultaneous => ConversionMode::OneShot,
+        }
+    }
+}
+
+/// A single thermocouple input on the board
+pub struct Channel<F> {
+    /// 1-based channel number, as printed on the board
+    pub number: u8,
+    pub frontend: F,
+}
+
+/// One temperature reading from one channel
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Reading {
+    /// Temperature in counts of 1/128°C (0 when faulted)
+    pub counts: i32,
+    /// Fault state latched during this reading
+    pub faults: Option<FaultStatus>,
+    /// Validity, fault and processing flags of this reading
+    pub quality: QualityFlags,
+    /// Time the conversion was read, in microseconds since boot
+    pub timestamp_us: u64,
+}
+
+impl Reading {
+    /// True if the counts are a real measurement that can be used
+    pub fn is_valid(&self) -> bool {
+        self.quality.contains(QualityFlags::VALID)
+    }
+}
+
+/// Time-aligned readings from the channels that were due
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SampleSet<const N: usize> {
+    /// Time all conversions completed, in microseconds since boot
+    pub timestamp_us: u64,
+    pub readings: [Option<Reading>; N],
+}
+
+/// Acquisition engine for `N` identical channels
+pub struct Acquisition<F, const N: usize> {
+    channels: [Channel<F>; N],
+}
+
+impl<F, const N: usize> Acquisition<F, N>
+where
+    F: ThermocoupleFrontend,
+{
+    /// Build the engine, numbering channels 1..=N in array order
+    pub fn new(frontends: [F; N]) -> Self {
+        let mut number = 0u8;
+        Self {
+            channels: frontends.map(|frontend| {
+                number += 1;
+                Channel { number, frontend }
+            }),
+        }
+    }
+
+    /// Configure every channel, logging its configuration
+    /// On failure, returns the number of the channel that failed
+    pub fn configure(&mut self) -> Result<(), (u8, F::Error)> {
+        for channel in self.channels.iter_mut() {
+            channel
+                .frontend
+                .configure()
+                .and_then(|_| channel.frontend.log_configuration(channel.number))
+                .map_err(|e| (channel.number, e))?;
+        }
+        Ok(())
+    }
+
+    /// Read each due channel once, in order
+    pub fn sample(&mut self, due: &[bool; N]) -> [Option<Reading>; N] {
+        let mut readings = [None; N];
+        for ((reading, channel), &due) in readings.iter_mut().zip(self.channels.iter_mut()).zip(due)
+        {
+            if !due {
+                continue;
+            }
+            let (counts, faults, quality) = match channel.frontend.read_with_fault_check() {
+                Ok((counts, faults)) => (counts, faults, quality::from_faults(faults.as_ref())),
+                Err(_) => (0, None, QualityFlags::SPI_ERROR),
+            };
+            *reading = Some(Reading {
+                counts,
+                faults,
+                quality,
+                timestamp_us: Instant::now().as_micros(),
+            });
+        }
+        readings
+    }
+
+    /// Trigger every due channel together, wait for all conversions and read them
+    ///
+    /// A channel that does not report data ready within `timeout` is read
+    /// anyway, so one dead converter cannot stall the others. Each reading
+    /// is stamped with the set's completion time rather than its SPI read.
+    pub async fn sample_simultaneous(
+        &mut self,
+        due: &[bool; N],
+        timeout: Duration,
+    ) -> SampleSet<N> {
+        for (channel, &due) in self.channels.iter_mut().zip(due) {
+            if due {
+                let _ = channel.frontend.trigger_conversion();
+            }
+        }
+
+        let mut channels = self.channels.iter_mut().zip(due);
+        let ready: [_; N] = core::array::from_fn(|_| {
+            let (channel, &due) = channels.next().unwrap();
+            let wait = channel.frontend.wait_ready();
+            async move {
+                if due {
+                    with_timeout(timeout, wait).await
+                } else {
+                    Ok(())
+                }
+            }
+        });
+        let ready = join_array(ready).await;
+        let timestamp_us = Instant::now().as_micros();
+
+        #[cfg(feature = "defmt")]
+        for (channel, ready) in self.channels.iter().zip(ready.iter()) {
+            if ready.is_err() {
+                defmt::warn!("Sensor {} - Data ready timeout", channel.number);
+            }
+        }
+
+        // Every channel converted together, so all share the completion time
+        let mut readings = self.sample(due);
+        for (reading, ready) in readings.iter_mut().zip(ready.iter()) {
+            if let Some(reading) = reading {
+                reading.timestamp_us = timestamp_us;
+                if ready.is_err() {
+                    reading.quality.insert(QualityFlags::STALE);
+                }
+            }
+        }
+
+        SampleSet {
+            timestamp_us,
+            readings,
+        }
+    }
+
+    pub fn channels(&self) -> &[Channel<F>; N] {
+        &self.channels
+    }
+
+    pub fn channels_mut(&mut self) -> &mut [Channel<F>; N] {
+        &mut self.channels
+    }
+}
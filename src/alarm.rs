@@ -0,0 +1,249 @@
+//! Threshold alarm engine
+//!
+//! Rules are evaluated on every sample set as it is acquired, so alarms are
+//! raised within one sample period instead of waiting for a full batch.
+//! Each rule trips when its metric rises above the threshold and only
+//! clears once the metric falls back below threshold - hysteresis, which
+//! stops a noisy signal sitting on the limit from chattering.
+
+use heapless::Vec;
+
+use crate::acquisition::Reading;
+
+/// Maximum number of configured rules
+pub const MAX_ALARM_RULES: usize = 16;
+
+/// What a rule watches; channels are 0-based indices, values are counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlarmCondition {
+    /// Temperature above `threshold`
+    Above { channel: u8, threshold: i32 },
+    /// Temperature below `threshold`
+    Below { channel: u8, threshold: i32 },
+    /// Temperature rising faster than `counts_per_s`
+    RateOfRise { channel: u8, counts_per_s: i32 },
+    /// `channel` hotter than `reference` by more than `threshold`
+    Delta {
+        channel: u8,
+        reference: u8,
+        threshold: i32,
+    },
+}
+
+/// A configured alarm rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AlarmRule {
+    /// Identifier reported in events
+    pub id: u8,
+    pub condition: AlarmCondition,
+    /// Distance back past the threshold before the alarm clears (counts,
+    /// or counts per second for rate rules)
+    pub hysteresis: i32,
+}
+
+impl AlarmRule {
+    /// Channel reported in events
+    pub const fn channel(&self) -> u8 {
+        match self.condition {
+            AlarmCondition::Above { channel, .. }
+            | AlarmCondition::Below { channel, .. }
+            | AlarmCondition::RateOfRise { channel, .. }
+            | AlarmCondition::Delta { channel, .. } => channel,
+        }
+    }
+
+    /// Second channel watched by delta rules
+    pub const fn reference(&self) -> Option<u8> {
+        match self.condition {
+            AlarmCondition::Delta { reference, .. } => Some(reference),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlarmTransition {
+    Raised,
+    Cleared,
+}
+
+/// A rule changing state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AlarmEvent {
+    pub rule_id: u8,
+    pub channel: u8,
+    pub transition: AlarmTransition,
+    /// Value of the watched metric when the transition happened
+    pub value: i32,
+    /// Sample time of the reading that caused the transition
+    pub timestamp_us: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlarmError {
+    TooManyRules,
+    /// The rule refers to a channel that does not exist
+    InvalidChannel,
+}
+
+/// Last valid sample of a channel, for rate-of-rise rules
+#[derive(Debug, Clone, Copy)]
+struct LastSample {
+    counts: i32,
+    timestamp_us: u64,
+}
+
+/// Rule evaluator for `N` channels
+pub struct AlarmEngine<const N: usize> {
+    rules: Vec<(AlarmRule, bool), MAX_ALARM_RULES>,
+    last: [Option<LastSample>; N],
+}
+
+impl<const N: usize> Default for AlarmEngine<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> AlarmEngine<N> {
+    pub const fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            last: [None; N],
+        }
+    }
+
+    /// Build an engine from a rule table
+    pub fn with_rules(rules: &[AlarmRule]) -> Result<Self, AlarmError> {
+        let mut engine = Self::new();
+        for rule in rules {
+            engine.add_rule(*rule)?;
+        }
+        Ok(engine)
+    }
+
+    pub fn add_rule(&mut self, rule: AlarmRule) -> Result<(), AlarmError> {
+        let channels_valid = match rule.condition {
+            AlarmCondition::Delta {
+                channel, reference, ..
+            } => (channel as usize) < N && (reference as usize) < N,
+            _ => (rule.channel() as usize) < N,
+        };
+        if !channels_valid {
+            return Err(AlarmError::InvalidChannel);
+        }
+        self.rules
+            .push((rule, false))
+            .map_err(|_| AlarmError::TooManyRules)
+    }
+
+    /// Remove every rule with the given id
+    pub fn remove_rule(&mut self, id: u8) {
+        self.rules.retain(|(rule, _)| rule.id != id);
+    }
+
+    /// Rules and whether each is currently active
+    pub fn rules(&self) -> impl Iterator<Item = (&AlarmRule, bool)> {
+        self.rules.iter().map(|(rule, active)| (rule, *active))
+    }
+
+    /// True if any rule is currently active
+    pub fn any_active(&self) -> bool {
+        self.rules.iter().any(|(_, active)| *active)
+    }
+
+    /// Forget each channel's previous sample, so no rate of rise is taken
+    /// across a gap in sampling. Active alarms stay active
+    pub fn reset_rates(&mut self) {
+        self.last = [None; N];
+    }
+
+    /// Evaluate all rules against a sample set, returning state changes
+    /// Faulted or missing readings leave the affected rules unchanged
+    pub fn evaluate(
+        &mut self,
+        readings: &[Option<Reading>; N],
+    ) -> Vec<AlarmEvent, MAX_ALARM_RULES> {
+        let valid = |channel: u8| {
+            readings[channel as usize]
+                .as_ref()
+                .filter(|reading| reading.is_valid())
+        };
+
+        let mut events = Vec::new();
+        for (rule, active) in self.rules.iter_mut() {
+            // Every condition is expressed as "metric above threshold"
+            let observed = match rule.condition {
+                AlarmCondition::Above { channel, threshold } => {
+                    valid(channel).map(|r| (r.counts, threshold, r.timestamp_us))
+                }
+                AlarmCondition::Below { channel, threshold } => {
+                    valid(channel).map(|r| (-r.counts, -threshold, r.timestamp_us))
+                }
+                AlarmCondition::RateOfRise {
+                    channel,
+                    counts_per_s,
+                } => valid(channel).and_then(|r| {
+                    let last = self.last[channel as usize]?;
+                    let dt_us = r.timestamp_us.checked_sub(last.timestamp_us)?;
+                    if dt_us == 0 {
+                        return None;
+                    }
+                    let rate = (r.counts as i64 - last.counts as i64) * 1_000_000 / dt_us as i64;
+                    // A large step over a short interval can exceed i32
+                    let rate = rate.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                    Some((rate, counts_per_s, r.timestamp_us))
+                }),
+                AlarmCondition::Delta {
+                    channel,
+                    reference,
+                    threshold,
+                } => valid(channel)
+                    .zip(valid(reference))
+                    .map(|(r, reference)| (r.counts - reference.counts, threshold, r.timestamp_us)),
+            };
+            let Some((metric, threshold, timestamp_us)) = observed else {
+                continue;
+            };
+
+            let transition = if !*active && metric > threshold {
+                *active = true;
+                AlarmTransition::Raised
+            } else if *active && metric < threshold - rule.hysteresis {
+                *active = false;
+                AlarmTransition::Cleared
+            } else {
+                continue;
+            };
+
+            // Report the metric in its natural sign
+            let value = match rule.condition {
+                AlarmCondition::Below { .. } => -metric,
+                _ => metric,
+            };
+            let _ = events.push(AlarmEvent {
+                rule_id: rule.id,
+                channel: rule.channel(),
+                transition,
+                value,
+                timestamp_us,
+            });
+        }
+
+        for (last, reading) in self.last.iter_mut().zip(readings) {
+            if let Some(reading) = reading.as_ref().filter(|r| r.is_valid()) {
+                *last = Some(LastSample {
+                    counts: reading.counts,
+                    timestamp_us: reading.timestamp_us,
+                });
+            }
+        }
+
+        events
+    }
+}
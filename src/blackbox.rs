@@ -0,0 +1,273 @@
+//! Pre/post-trigger black-box recorder
+//!
+//! Every reading is kept in a RAM ring buffer covering the last few seconds
+//! of full-rate data. An alarm or trigger starts the post-trigger phase; once
+//! that has been recorded the buffer freezes, holding the run-up to the event
+//! and its aftermath until it is downloaded and re-armed.
+//!
+//! Recording pauses while a download is sent, so the contents stay still.
+//! Events and freezes during a download still change the state as usual, and
+//! recording carries on from that state once the download ends.
+//!
+//! Downloads are a header followed by the records, oldest first, all
+//! little-endian:
+//!
+//! | Offset | Field | Type |
+//! |--------|-------|------|
+//! | 0 | magic `TSBB` | `[u8; 4]` |
+//! | 4 | format version | `u16` |
+//! | 6 | channel count | `u8` |
+//! | 7 | freeze reason | `u8` |
+//! | 8 | record count | `u32` |
+//! | 12 | record length | `u16` |
+//! | 14 | reserved | `u16` |
+//! | 16 | freeze event time (µs) | `u64` |
+//!
+//! Each record is the sample time (`u64` µs), channel index (`u8`), MAX31856
+//! fault register bits (`u8`), quality flags (`u16`) and counts (`i32`).
+
+use crate::acquisition::Reading;
+
+pub const BLACKBOX_MAGIC: [u8; 4] = *b"TSBB";
+pub const BLACKBOX_VERSION: u16 = 1;
+pub const BLACKBOX_HEADER_LEN: usize = 24;
+pub const BLACKBOX_RECORD_LEN: usize = 16;
+
+/// Records needed to hold `seconds` of data at the given per-channel rates
+pub const fn blackbox_capacity<const N: usize>(rates_mhz: &[u32; N], seconds: u32) -> usize {
+    let mut total_mhz = 0u64;
+    let mut i = 0;
+    while i < N {
+        total_mhz += rates_mhz[i] as u64;
+        i += 1;
+    }
+    // One extra sample per channel covers rounding at the window edges
+    (total_mhz * seconds as u64).div_ceil(1000) as usize + N
+}
+
+/// One stored reading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlackBoxRecord {
+    /// Sample time in microseconds
+    pub timestamp_us: u64,
+    /// Channel index (0-based)
+    pub channel: u8,
+    /// MAX31856 fault register bits, 0 if the reading is valid
+    pub faults: u8,
+    /// Quality flag bits
+    pub quality: u16,
+    /// Temperature in counts of 1/128°C
+    pub counts: i32,
+}
+
+impl BlackBoxRecord {
+    pub fn from_reading(channel: u8, reading: &Reading) -> Self {
+        Self {
+            timestamp_us: reading.timestamp_us,
+            channel,
+            faults: reading.faults.map_or(0, |faults| faults.to_register()),
+            quality: reading.quality.bits(),
+            counts: reading.counts,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; BLACKBOX_RECORD_LEN] {
+        let mut bytes = [0; BLACKBOX_RECORD_LEN];
+        bytes[0..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes[8] = self.channel;
+        bytes[9] = self.faults;
+        bytes[10..12].copy_from_slice(&self.quality.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.counts.to_le_bytes());
+        bytes
+    }
+}
+
+/// Why the buffer was frozen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum FreezeReason {
+    Alarm = 1,
+    Trigger = 2,
+    /// Freeze command or a download of a live buffer
+    Manual = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BlackBoxState {
+    /// Recording, waiting for a freeze event
+    Armed,
+    /// Recording the post-trigger window
+    PostTrigger {
+        reason: FreezeReason,
+        event_us: u64,
+        stop_us: u64,
+    },
+    /// Not recording; contents held for download
+    Frozen { reason: FreezeReason, event_us: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BlackBoxError {
+    /// Re-arming would discard a capture while it is being sent
+    Downloading,
+}
+
+/// Ring buffer of the last `CAP` readings
+pub struct BlackBox<const CAP: usize> {
+    records: [BlackBoxRecord; CAP],
+    head: usize,
+    len: usize,
+    post_trigger_us: u64,
+    state: BlackBoxState,
+    downloading: bool,
+}
+
+impl<const CAP: usize> BlackBox<CAP> {
+    /// Keep recording for `post_trigger_us` after a freeze event
+    pub const fn new(post_trigger_us: u64) -> Self {
+        Self {
+            records: [BlackBoxRecord {
+                timestamp_us: 0,
+                channel: 0,
+                faults: 0,
+                quality: 0,
+                counts: 0,
+            }; CAP],
+            head: 0,
+            len: 0,
+            post_trigger_us,
+            state: BlackBoxState::Armed,
+            downloading: false,
+        }
+    }
+
+    pub fn state(&self) -> BlackBoxState {
+        self.state
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        matches!(self.state, BlackBoxState::Frozen { .. })
+    }
+
+    /// Stored records
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Store a sample set, overwriting the oldest records when full
+    /// Returns true if this set completed the post-trigger window
+    /// Nothing is stored during a download; a post-trigger window that ran
+    /// out meanwhile completes with the first set after it
+    pub fn record<const N: usize>(&mut self, readings: &[Option<Reading>; N]) -> bool {
+        if self.is_frozen() || self.downloading {
+            return false;
+        }
+        let mut latest_us = 0;
+        for (channel, reading) in readings.iter().enumerate() {
+            let Some(reading) = reading else {
+                continue;
+            };
+            self.records[self.head] = BlackBoxRecord::from_reading(channel as u8, reading);
+            self.head = (self.head + 1) % CAP;
+            self.len = (self.len + 1).min(CAP);
+            latest_us = latest_us.max(reading.timestamp_us);
+        }
+
+        if let BlackBoxState::PostTrigger {
+            reason,
+            event_us,
+            stop_us,
+        } = self.state
+            && latest_us >= stop_us
+        {
+            self.state = BlackBoxState::Frozen { reason, event_us };
+            return true;
+        }
+        false
+    }
+
+    /// Start the post-trigger window for an event at `event_us`
+    /// Ignored unless armed, so the first event is the one kept
+    pub fn trigger(&mut self, reason: FreezeReason, event_us: u64) {
+        if self.state == BlackBoxState::Armed {
+            self.state = BlackBoxState::PostTrigger {
+                reason,
+                event_us,
+                stop_us: event_us + self.post_trigger_us,
+            };
+        }
+    }
+
+    /// Stop recording immediately
+    /// A pending post-trigger window is cut short but keeps its event
+    pub fn freeze(&mut self, now_us: u64) {
+        self.state = match self.state {
+            BlackBoxState::Armed => BlackBoxState::Frozen {
+                reason: FreezeReason::Manual,
+                event_us: now_us,
+            },
+            BlackBoxState::PostTrigger {
+                reason, event_us, ..
+            } => BlackBoxState::Frozen { reason, event_us },
+            frozen => frozen,
+        };
+    }
+
+    /// Resume recording, keeping the current contents until overwritten
+    /// Refused during a download, which would lose any capture being sent
+    pub fn arm(&mut self) -> Result<(), BlackBoxError> {
+        if self.downloading {
+            return Err(BlackBoxError::Downloading);
+        }
+        self.state = BlackBoxState::Armed;
+        Ok(())
+    }
+
+    /// Pause recording for a download, returning its header
+    /// A buffer that is still armed is described as a manual freeze at `now_us`
+    pub fn start_download<const N: usize>(&mut self, now_us: u64) -> [u8; BLACKBOX_HEADER_LEN] {
+        self.downloading = true;
+        self.header::<N>(now_us)
+    }
+
+    /// Resume recording from whatever state events during the download left
+    pub fn end_download(&mut self) {
+        self.downloading = false;
+    }
+
+    /// Download header for the current contents
+    /// An armed buffer is reported as a manual freeze at `now_us`
+    pub fn header<const N: usize>(&self, now_us: u64) -> [u8; BLACKBOX_HEADER_LEN] {
+        let (reason, event_us) = match self.state {
+            BlackBoxState::Armed => (FreezeReason::Manual as u8, now_us),
+            BlackBoxState::PostTrigger {
+                reason, event_us, ..
+            }
+            | BlackBoxState::Frozen { reason, event_us } => (reason as u8, event_us),
+        };
+        let mut bytes = [0; BLACKBOX_HEADER_LEN];
+        bytes[0..4].copy_from_slice(&BLACKBOX_MAGIC);
+        bytes[4..6].copy_from_slice(&BLACKBOX_VERSION.to_le_bytes());
+        bytes[6] = N as u8;
+        bytes[7] = reason;
+        bytes[8..12].copy_from_slice(&(self.len as u32).to_le_bytes());
+        bytes[12..14].copy_from_slice(&(BLACKBOX_RECORD_LEN as u16).to_le_bytes());
+        bytes[16..24].copy_from_slice(&event_us.to_le_bytes());
+        bytes
+    }
+
+    /// Record `index`, counting from the oldest
+    pub fn get(&self, index: usize) -> Option<&BlackBoxRecord> {
+        if index >= self.len {
+            return None;
+        }
+        Some(&self.records[(self.head + CAP - self.len + index) % CAP])
+    }
+}
@@ -0,0 +1,66 @@
+//! Persistent boot counter
+//!
+//! Each boot appends a small record to a dedicated flash sector instead of
+//! rewriting one word, so the sector is erased only once every few hundred
+//! boots. The count is sent in packet headers as the epoch, letting receivers
+//! tell a restarted board's sequence numbers from the previous run's.
+//!
+//! Each record is the magic `TSBC`, the count and a CRC-32 of both, all
+//! little-endian, padded with erased bytes to `BOOT_RECORD_LEN`.
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::crc::crc32;
+
+const BOOT_MAGIC: [u8; 4] = *b"TSBC";
+
+/// Flash space taken by one record, a multiple of the flash write size
+pub const BOOT_RECORD_LEN: usize = 16;
+
+/// Decode one record slot; `None` if it is erased or corrupt
+fn decode(bytes: &[u8; BOOT_RECORD_LEN]) -> Option<u32> {
+    let crc = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    if bytes[0..4] != BOOT_MAGIC || crc32(&bytes[0..8]) != crc {
+        return None;
+    }
+    Some(u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]))
+}
+
+/// Count this boot in the sector at `offset` and return the new count
+/// The first boot is 1; a corrupt or foreign sector starts again from 1
+pub fn increment_boot_count<F: NorFlash>(flash: &mut F, offset: u32) -> Result<u32, F::Error> {
+    assert!(
+        BOOT_RECORD_LEN.is_multiple_of(F::WRITE_SIZE),
+        "Boot record must be a whole number of flash writes"
+    );
+    let slots = F::ERASE_SIZE / BOOT_RECORD_LEN;
+
+    // The highest count wins; the next record goes after the last used slot
+    let mut count = 0;
+    let mut next_slot = 0;
+    let mut slot = [0u8; BOOT_RECORD_LEN];
+    for index in 0..slots {
+        flash.read(offset + (index * BOOT_RECORD_LEN) as u32, &mut slot)?;
+        if slot.iter().all(|&byte| byte == 0xFF) {
+            continue;
+        }
+        if let Some(stored) = decode(&slot) {
+            count = count.max(stored);
+        }
+        next_slot = index + 1;
+    }
+
+    if next_slot == slots {
+        flash.erase(offset, offset + F::ERASE_SIZE as u32)?;
+        next_slot = 0;
+    }
+
+    let count = count.wrapping_add(1).max(1);
+    let mut record = [0xFFu8; BOOT_RECORD_LEN];
+    record[0..4].copy_from_slice(&BOOT_MAGIC);
+    record[4..8].copy_from_slice(&count.to_le_bytes());
+    let crc = crc32(&record[0..8]);
+    record[8..12].copy_from_slice(&crc.to_le_bytes());
+    flash.write(offset + (next_slot * BOOT_RECORD_LEN) as u32, &record)?;
+    Ok(count)
+}
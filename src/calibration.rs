@@ -0,0 +1,220 @@
+//! Per-channel calibration and its storage in flash
+//!
+//! The corrections are in the `thermosoft-calibration` crate
+//! (`calibration/`), which is tested on the host; this module applies them
+//! to the board's readings.
+//!
+//! Calibrations are stored in one flash sector as a header, one fixed-size
+//! record per channel and a CRC-32, all little-endian.
+
+pub use thermosoft_calibration::*;
+
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+use crate::acquisition::Reading;
+use crate::crc::crc32;
+
+/// Stored format identifier and version
+const STORAGE_MAGIC: [u8; 4] = *b"TSCL";
+const STORAGE_VERSION: u16 = 1;
+const STORAGE_HEADER_LEN: usize = 8;
+const STORAGE_RECORD_LEN: usize = 104;
+const STORAGE_CRC_LEN: usize = 4;
+
+/// Largest encoded bank, sized for up to 8 channels
+const STORAGE_BUFFER_LEN: usize = 1024;
+
+/// Store one channel's record in `STORAGE_RECORD_LEN` bytes
+fn encode_record(calibration: &ChannelCalibration, bytes: &mut [u8]) {
+    let (kind, len) = match calibration.correction {
+        Correction::None => (0u8, 0u8),
+        Correction::Polynomial(_) => (1, MAX_POLYNOMIAL_TERMS as u8),
+        Correction::Table { len, .. } => (2, len),
+    };
+    bytes[0..4].copy_from_slice(&calibration.id.to_le_bytes());
+    bytes[4..8].copy_from_slice(&calibration.date.to_le_bytes());
+    bytes[8..12].copy_from_slice(&calibration.offset_counts.to_le_bytes());
+    bytes[12..16].copy_from_slice(&calibration.gain.to_le_bytes());
+    bytes[16..20].copy_from_slice(&calibration.cj_offset_counts.to_le_bytes());
+    bytes[20] = kind;
+    bytes[21] = len;
+    bytes[22..STORAGE_RECORD_LEN].fill(0);
+    match calibration.correction {
+        Correction::None => {}
+        Correction::Polynomial(coefficients) => {
+            for (chunk, c) in bytes[24..40].chunks_exact_mut(4).zip(coefficients) {
+                chunk.copy_from_slice(&c.to_le_bytes());
+            }
+        }
+        Correction::Table { points, .. } => {
+            for (chunk, (measured, actual)) in bytes[40..104].chunks_exact_mut(8).zip(points) {
+                chunk[0..4].copy_from_slice(&measured.to_le_bytes());
+                chunk[4..8].copy_from_slice(&actual.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Read one channel's record, checking it is usable
+fn decode_record(bytes: &[u8]) -> Result<ChannelCalibration, CalibrationError> {
+    let u32_at =
+        |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    let correction = match bytes[20] {
+        0 => Correction::None,
+        1 => {
+            let mut coefficients = [0.0; MAX_POLYNOMIAL_TERMS];
+            for (i, c) in coefficients.iter_mut().enumerate() {
+                *c = f32::from_bits(u32_at(24 + 4 * i));
+            }
+            Correction::Polynomial(coefficients)
+        }
+        2 => {
+            let mut points = [(0, 0); MAX_TABLE_POINTS];
+            for (i, point) in points.iter_mut().enumerate() {
+                *point = (u32_at(40 + 8 * i) as i32, u32_at(44 + 8 * i) as i32);
+            }
+            Correction::Table {
+                points,
+                len: bytes[21],
+            }
+        }
+        _ => return Err(CalibrationError::InvalidTable),
+    };
+    let calibration = ChannelCalibration {
+        id: u32_at(0),
+        date: u32_at(4),
+        offset_counts: u32_at(8) as i32,
+        gain: f32::from_bits(u32_at(12)),
+        cj_offset_counts: u32_at(16) as i32,
+        correction,
+    };
+    calibration.validate()?;
+    Ok(calibration)
+}
+
+/// Calibration of every channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationBank<const N: usize> {
+    channels: [ChannelCalibration; N],
+}
+
+impl<const N: usize> CalibrationBank<N> {
+    /// Encoded length, padded to `write_size`
+    const fn storage_len(write_size: usize) -> usize {
+        (STORAGE_HEADER_LEN + N * STORAGE_RECORD_LEN + STORAGE_CRC_LEN).next_multiple_of(write_size)
+    }
+
+    pub fn new(channels: [ChannelCalibration; N]) -> Result<Self, CalibrationError> {
+        for channel in &channels {
+            channel.validate()?;
+        }
+        Ok(Self { channels })
+    }
+
+    pub fn channels(&self) -> &[ChannelCalibration; N] {
+        &self.channels
+    }
+
+    /// Replace one channel's calibration (0-based index)
+    pub fn set_channel(
+        &mut self,
+        index: usize,
+        calibration: ChannelCalibration,
+    ) -> Result<(), CalibrationError> {
+        calibration.validate()?;
+        self.channels[index] = calibration;
+        Ok(())
+    }
+
+    /// Correct a sample set in place; faulted readings are left as they are
+    pub fn apply(&self, readings: &mut [Option<Reading>; N]) {
+        for (calibration, reading) in self.channels.iter().zip(readings.iter_mut()) {
+            if let Some(reading) = reading
+                && reading.is_valid()
+            {
+                reading.counts = calibration.apply(reading.counts);
+            }
+        }
+    }
+
+    fn encode(&self, bytes: &mut [u8]) -> usize {
+        bytes[0..4].copy_from_slice(&STORAGE_MAGIC);
+        bytes[4..6].copy_from_slice(&STORAGE_VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(N as u16).to_le_bytes());
+        let records = &mut bytes[STORAGE_HEADER_LEN..STORAGE_HEADER_LEN + N * STORAGE_RECORD_LEN];
+        for (record, channel) in records
+            .chunks_exact_mut(STORAGE_RECORD_LEN)
+            .zip(&self.channels)
+        {
+            encode_record(channel, record);
+        }
+        let crc_at = STORAGE_HEADER_LEN + N * STORAGE_RECORD_LEN;
+        let crc = crc32(&bytes[..crc_at]);
+        bytes[crc_at..crc_at + STORAGE_CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+        crc_at + STORAGE_CRC_LEN
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, CalibrationError> {
+        if bytes[0..4] != STORAGE_MAGIC {
+            return Err(CalibrationError::NotFound);
+        }
+        if u16::from_le_bytes([bytes[4], bytes[5]]) != STORAGE_VERSION {
+            return Err(CalibrationError::UnsupportedVersion);
+        }
+        if u16::from_le_bytes([bytes[6], bytes[7]]) as usize != N {
+            return Err(CalibrationError::ChannelCountMismatch);
+        }
+        let crc_at = STORAGE_HEADER_LEN + N * STORAGE_RECORD_LEN;
+        let stored_crc = u32::from_le_bytes([
+            bytes[crc_at],
+            bytes[crc_at + 1],
+            bytes[crc_at + 2],
+            bytes[crc_at + 3],
+        ]);
+        if crc32(&bytes[..crc_at]) != stored_crc {
+            return Err(CalibrationError::ChecksumMismatch);
+        }
+
+        let mut channels = [ChannelCalibration::IDENTITY; N];
+        let records = &bytes[STORAGE_HEADER_LEN..crc_at];
+        for (channel, record) in channels
+            .iter_mut()
+            .zip(records.chunks_exact(STORAGE_RECORD_LEN))
+        {
+            *channel = decode_record(record)?;
+        }
+        Ok(Self { channels })
+    }
+
+    /// Read the bank stored at `offset`
+    pub fn load<F: ReadNorFlash>(flash: &mut F, offset: u32) -> Result<Self, CalibrationError> {
+        let mut buffer = [0u8; STORAGE_BUFFER_LEN];
+        let len = Self::storage_len(F::READ_SIZE);
+        assert!(
+            len <= STORAGE_BUFFER_LEN,
+            "Too many channels for calibration storage"
+        );
+        flash
+            .read(offset, &mut buffer[..len])
+            .map_err(|_| CalibrationError::Storage)?;
+        Self::decode(&buffer)
+    }
+
+    /// Erase the sector at `offset` and store the bank there
+    /// Blocks for the duration of the erase, typically tens of milliseconds
+    pub fn save<F: NorFlash>(&self, flash: &mut F, offset: u32) -> Result<(), CalibrationError> {
+        let mut buffer = [0xFFu8; STORAGE_BUFFER_LEN];
+        let len = Self::storage_len(F::WRITE_SIZE);
+        assert!(
+            len <= STORAGE_BUFFER_LEN,
+            "Too many channels for calibration storage"
+        );
+        self.encode(&mut buffer);
+        flash
+            .erase(offset, offset + F::ERASE_SIZE as u32)
+            .map_err(|_| CalibrationError::Storage)?;
+        flash
+            .write(offset, &buffer[..len])
+            .map_err(|_| CalibrationError::Storage)
+    }
+}
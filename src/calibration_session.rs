@@ -0,0 +1,277 @@
+//! Guided calibration of one channel against operator-supplied references
+//!
+//! A session selects a channel, then captures one point per reference
+//! temperature: the channel's uncalibrated counts are averaged over a number
+//! of samples while the sensor sits in an ice bath, dry block or similar.
+//! Once enough points are captured a correction is fitted to them and
+//! checked, and the result can be committed as the channel's calibration.
+//! The cold-junction offset is not part of the fit and is kept as it was.
+//! The fits are in the `thermosoft-calibration` crate, tested on the host.
+
+pub use crate::calibration::fit::{CalibrationPoint, Fit, FitKind, MAX_FIT_RESIDUAL_COUNTS};
+
+use core::fmt::Write;
+
+use heapless::Vec;
+
+use crate::acquisition::Reading;
+use crate::calibration::fit::FitError;
+use crate::calibration::{CalibrationError, ChannelCalibration, MAX_TABLE_POINTS};
+use crate::stats::RunningStats;
+
+/// Most reference points in one session
+pub const MAX_CALIBRATION_POINTS: usize = MAX_TABLE_POINTS;
+
+/// Samples averaged per point unless the operator asks for another number
+pub const DEFAULT_CAPTURE_SAMPLES: u16 = 32;
+
+/// Points noisier than this (standard deviation, counts) are rejected as
+/// not yet settled at the reference temperature
+pub const MAX_CAPTURE_STDDEV_COUNTS: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SessionError {
+    /// No session has been started
+    NotStarted,
+    InvalidChannel,
+    /// A point is still being captured
+    Busy,
+    TooManyPoints,
+    /// The fit needs more points than have been captured
+    NotEnoughPoints,
+    /// The channel faulted during a capture
+    Faulted,
+    /// The captured samples were too noisy
+    Unstable,
+    /// Two points have the same measured counts
+    Degenerate,
+    /// The fit misses a point by more than `MAX_FIT_RESIDUAL_COUNTS`
+    PoorFit,
+    /// Nothing has been fitted since the last point was captured
+    NotFitted,
+    Invalid(CalibrationError),
+}
+
+impl SessionError {
+    pub const fn message(&self) -> &'static str {
+        match self {
+            SessionError::NotStarted => "no calibration in progress",
+            SessionError::InvalidChannel => "no such channel",
+            SessionError::Busy => "capture in progress",
+            SessionError::TooManyPoints => "too many points",
+            SessionError::NotEnoughPoints => "not enough points",
+            SessionError::Faulted => "channel faulted",
+            SessionError::Unstable => "reading not stable",
+            SessionError::Degenerate => "duplicate points",
+            SessionError::PoorFit => "fit error too large",
+            SessionError::NotFitted => "no fit computed",
+            SessionError::Invalid(error) => error.message(),
+        }
+    }
+}
+
+impl From<FitError> for SessionError {
+    fn from(error: FitError) -> Self {
+        match error {
+            FitError::NotEnoughPoints => SessionError::NotEnoughPoints,
+            FitError::Degenerate => SessionError::Degenerate,
+            FitError::PoorFit => SessionError::PoorFit,
+            FitError::Invalid(error) => SessionError::Invalid(error),
+        }
+    }
+}
+
+impl From<CalibrationError> for SessionError {
+    fn from(error: CalibrationError) -> Self {
+        SessionError::Invalid(error)
+    }
+}
+
+/// A point being averaged
+#[derive(Debug, Clone, Copy)]
+struct Capture {
+    reference: i32,
+    remaining: u16,
+    stats: RunningStats,
+}
+
+/// Calibration procedure for one of `N` channels at a time
+#[derive(Debug, Clone)]
+pub struct CalibrationSession<const N: usize> {
+    channel: Option<usize>,
+    samples: u16,
+    points: Vec<CalibrationPoint, MAX_CALIBRATION_POINTS>,
+    capture: Option<Capture>,
+    /// Outcome of the last finished capture
+    last_capture: Option<Result<CalibrationPoint, SessionError>>,
+    fit: Option<Fit>,
+}
+
+impl<const N: usize> Default for CalibrationSession<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CalibrationSession<N> {
+    pub const fn new() -> Self {
+        Self {
+            channel: None,
+            samples: DEFAULT_CAPTURE_SAMPLES,
+            points: Vec::new(),
+            capture: None,
+            last_capture: None,
+            fit: None,
+        }
+    }
+
+    /// Channel being calibrated (0-based)
+    pub fn channel(&self) -> Option<usize> {
+        self.channel
+    }
+
+    pub fn points(&self) -> &[CalibrationPoint] {
+        &self.points
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Begin calibrating `channel`, discarding any earlier session
+    pub fn start(&mut self, channel: usize, samples: Option<u16>) -> Result<(), SessionError> {
+        if channel >= N {
+            return Err(SessionError::InvalidChannel);
+        }
+        *self = Self::new();
+        self.channel = Some(channel);
+        self.samples = samples.unwrap_or(DEFAULT_CAPTURE_SAMPLES).max(1);
+        Ok(())
+    }
+
+    /// End the session without changing the calibration
+    pub fn abort(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Start averaging a point at the reference temperature `reference` (counts)
+    pub fn capture(&mut self, reference: i32) -> Result<(), SessionError> {
+        if self.channel.is_none() {
+            return Err(SessionError::NotStarted);
+        }
+        if self.capture.is_some() {
+            return Err(SessionError::Busy);
+        }
+        if self.points.is_full() {
+            return Err(SessionError::TooManyPoints);
+        }
+        self.capture = Some(Capture {
+            reference,
+            remaining: self.samples,
+            stats: RunningStats::new(),
+        });
+        self.last_capture = None;
+        Ok(())
+    }
+
+    /// Feed an uncalibrated sample set to a running capture
+    /// Returns the outcome once the capture finishes
+    pub fn add(
+        &mut self,
+        readings: &[Option<Reading>; N],
+    ) -> Option<Result<CalibrationPoint, SessionError>> {
+        let (Some(channel), Some(capture)) = (self.channel, self.capture.as_mut()) else {
+            return None;
+        };
+        let reading = readings[channel].as_ref()?;
+
+        let outcome = if !reading.is_valid() {
+            Err(SessionError::Faulted)
+        } else {
+            capture.stats.add(reading.counts);
+            capture.remaining -= 1;
+            if capture.remaining > 0 {
+                return None;
+            }
+            let stats = capture.stats.finish();
+            if stats.stddev > MAX_CAPTURE_STDDEV_COUNTS {
+                Err(SessionError::Unstable)
+            } else {
+                Ok(CalibrationPoint {
+                    measured: stats.mean,
+                    reference: capture.reference,
+                    stddev: stats.stddev,
+                })
+            }
+        };
+
+        self.capture = None;
+        if let Ok(point) = outcome {
+            // Room was checked when the capture started
+            let _ = self.points.push(point);
+            self.fit = None;
+        }
+        self.last_capture = Some(outcome);
+        Some(outcome)
+    }
+
+    /// Fit a correction to the captured points and check it
+    pub fn fit(&mut self, kind: FitKind) -> Result<Fit, SessionError> {
+        if self.channel.is_none() {
+            return Err(SessionError::NotStarted);
+        }
+        if self.capture.is_some() {
+            return Err(SessionError::Busy);
+        }
+        let fit = Fit::new(kind, &self.points)?;
+        self.fit = Some(fit);
+        Ok(fit)
+    }
+
+    /// Calibration record from the last fit, keeping the cold-junction offset
+    /// of the `current` record. Returns the channel it belongs to; the session
+    /// is left open until `finish` so a failed store can be retried
+    pub fn result(
+        &self,
+        id: u32,
+        date: u32,
+        current: &[ChannelCalibration; N],
+    ) -> Result<(usize, ChannelCalibration), SessionError> {
+        let channel = self.channel.ok_or(SessionError::NotStarted)?;
+        let fit = self.fit.ok_or(SessionError::NotFitted)?;
+        let calibration = fit.calibration(id, date, current[channel].cj_offset_counts);
+        Ok((channel, calibration))
+    }
+
+    /// End the session after its result was committed
+    pub fn finish(&mut self) {
+        self.abort();
+    }
+
+    /// Describe the session in one line of text
+    pub fn write_status(&self, out: &mut impl Write) -> core::fmt::Result {
+        let Some(channel) = self.channel else {
+            return out.write_str("idle");
+        };
+        write!(out, "channel {} points {}", channel, self.points.len())?;
+        if let Some(capture) = &self.capture {
+            write!(
+                out,
+                " capturing {}/{}",
+                self.samples - capture.remaining,
+                self.samples
+            )?;
+        } else if let Some(Err(error)) = &self.last_capture {
+            write!(out, " last capture failed: {}", error.message())?;
+        }
+        if let Some(fit) = &self.fit {
+            write!(
+                out,
+                " fit gain {} offset {} residual {}",
+                fit.gain, fit.offset_counts, fit.max_residual_counts
+            )?;
+        }
+        Ok(())
+    }
+}
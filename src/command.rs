@@ -0,0 +1,199 @@
+//! Text commands received over UDP
+//!
+//! Each datagram holds one command of whitespace-separated words, e.g.
+//! `interlock reset 0`. The board answers every datagram with `OK` or
+//! `ERR <reason>`, so commands can be sent by hand with netcat. Some
+//! commands add a line of text after the `OK`.
+
+use heapless::String;
+
+use crate::calibration_session::FitKind;
+use crate::filter::FilterPreset;
+use crate::frontend::COUNTS_PER_DEGREE_C;
+
+/// Longest text that can follow `OK` in a reply
+pub const REPLY_TEXT_LEN: usize = 96;
+
+/// References beyond this are rejected as typing errors, in °C
+const MAX_REFERENCE_C: f32 = 2000.0;
+
+/// A parsed command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// Release a latched interlock output, or all of them if `None`
+    InterlockReset(Option<u8>),
+    /// Resume black-box recording after a freeze
+    BlackBoxArm,
+    /// Freeze the black-box recorder now
+    BlackBoxFreeze,
+    /// Start calibrating a channel (0-based), averaging `samples` per point
+    CalibrationStart { channel: u8, samples: Option<u16> },
+    /// Capture a point at a reference temperature, in counts of 1/128°C
+    CalibrationPoint { reference_counts: i32 },
+    /// Fit a correction to the captured points
+    CalibrationFit(FitKind),
+    /// Report the progress of the calibration
+    CalibrationStatus,
+    /// Store the fitted correction with a run ID and a YYYYMMDD date
+    CalibrationCommit { id: u32, date: u32 },
+    /// End the calibration without storing anything
+    CalibrationAbort,
+    /// Switch a channel (0-based) to a preset filter
+    FilterSelect { channel: u8, preset: FilterPreset },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CommandError {
+    /// Not valid UTF-8 or not a known command
+    Unknown,
+    /// Known command with a missing or malformed argument
+    BadArgument,
+    /// Parsed, but refused by the firmware
+    Rejected(&'static str),
+    /// The sampling loop did not answer in time
+    Timeout,
+}
+
+impl CommandError {
+    pub const fn message(&self) -> &'static str {
+        match self {
+            CommandError::Unknown => "unknown command",
+            CommandError::BadArgument => "bad argument",
+            CommandError::Rejected(reason) => reason,
+            CommandError::Timeout => "timeout",
+        }
+    }
+}
+
+/// Text sent after `OK`, usually empty
+pub type ReplyText = String<REPLY_TEXT_LEN>;
+
+/// Result of running a command
+pub type CommandResult = Result<ReplyText, CommandError>;
+
+impl Command {
+    /// Parse one datagram
+    pub fn parse(datagram: &[u8]) -> Result<Self, CommandError> {
+        let text = core::str::from_utf8(datagram).map_err(|_| CommandError::Unknown)?;
+        let mut words = text.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("interlock"), Some("reset")) => {
+                let index = match words.next() {
+                    None | Some("all") => None,
+                    Some(index) => Some(index.parse().map_err(|_| CommandError::BadArgument)?),
+                };
+                finish(words, Command::InterlockReset(index))
+            }
+            (Some("blackbox"), Some("arm")) => finish(words, Command::BlackBoxArm),
+            (Some("blackbox"), Some("freeze")) => finish(words, Command::BlackBoxFreeze),
+            (Some("cal"), Some("start")) => {
+                let channel = parse_next(&mut words)?;
+                let samples = match words.next() {
+                    None => None,
+                    Some(samples) => match samples.parse() {
+                        Ok(0) | Err(_) => return Err(CommandError::BadArgument),
+                        Ok(samples) => Some(samples),
+                    },
+                };
+                finish(words, Command::CalibrationStart { channel, samples })
+            }
+            (Some("cal"), Some("point")) => {
+                let celsius: f32 = parse_next(&mut words)?;
+                if !(-MAX_REFERENCE_C..=MAX_REFERENCE_C).contains(&celsius) {
+                    return Err(CommandError::BadArgument);
+                }
+                let counts = celsius * COUNTS_PER_DEGREE_C as f32;
+                let reference_counts = if counts >= 0.0 {
+                    counts + 0.5
+                } else {
+                    counts - 0.5
+                } as i32;
+                finish(words, Command::CalibrationPoint { reference_counts })
+            }
+            (Some("cal"), Some("fit")) => {
+                let kind = match words.next() {
+                    None | Some("linear") => FitKind::Linear,
+                    Some("poly") => FitKind::Polynomial,
+                    Some("table") => FitKind::Table,
+                    Some(_) => return Err(CommandError::BadArgument),
+                };
+                finish(words, Command::CalibrationFit(kind))
+            }
+            (Some("cal"), Some("status")) => finish(words, Command::CalibrationStatus),
+            (Some("cal"), Some("commit")) => {
+                let id = parse_next(&mut words)?;
+                let date = parse_next(&mut words)?;
+                if !is_valid_date(date) {
+                    return Err(CommandError::BadArgument);
+                }
+                finish(words, Command::CalibrationCommit { id, date })
+            }
+            (Some("cal"), Some("abort")) => finish(words, Command::CalibrationAbort),
+            (Some("filter"), Some(channel)) => {
+                let channel = channel.parse().map_err(|_| CommandError::BadArgument)?;
+                let preset = words
+                    .next()
+                    .and_then(FilterPreset::from_name)
+                    .ok_or(CommandError::BadArgument)?;
+                finish(words, Command::FilterSelect { channel, preset })
+            }
+            _ => Err(CommandError::Unknown),
+        }
+    }
+}
+
+/// Reject trailing words after a complete command
+fn finish<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+    command: Command,
+) -> Result<Command, CommandError> {
+    match words.next() {
+        None => Ok(command),
+        Some(_) => Err(CommandError::BadArgument),
+    }
+}
+
+/// Parse the next word as a required argument
+fn parse_next<'a, T: core::str::FromStr>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<T, CommandError> {
+    words
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or(CommandError::BadArgument)
+}
+
+/// Loose check of a YYYYMMDD date
+fn is_valid_date(date: u32) -> bool {
+    let (year, month, day) = (date / 10000, date / 100 % 100, date % 100);
+    (2000..=2099).contains(&year) && (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Write the reply to a command into `buffer`, returning its length
+pub fn format_reply(result: &CommandResult, buffer: &mut [u8]) -> usize {
+    let mut length = 0;
+    let mut push = |text: &str| {
+        for &byte in text.as_bytes() {
+            if length < buffer.len() {
+                buffer[length] = byte;
+                length += 1;
+            }
+        }
+    };
+    match result {
+        Ok(text) if text.is_empty() => push("OK\n"),
+        Ok(text) => {
+            push("OK ");
+            push(text);
+            push("\n");
+        }
+        Err(error) => {
+            push("ERR ");
+            push(error.message());
+            push("\n");
+        }
+    }
+    length
+}
@@ -0,0 +1,78 @@
+//! Per-channel filtering and decimation of sample sets
+//!
+//! The fixed-point filters themselves are in the `thermosoft-filter` crate
+//! (`filter/`), which is tested on the host; this module applies them to
+//! the board's readings.
+
+pub use thermosoft_filter::*;
+
+use crate::acquisition::Reading;
+use crate::quality::QualityFlags;
+
+/// Filters for every channel, each keeping every `decimation`-th output
+pub struct FilterBank<const N: usize> {
+    filters: [ChannelFilter; N],
+    decimators: [Decimator; N],
+}
+
+impl<const N: usize> FilterBank<N> {
+    /// Build a bank from per-channel configs, keeping every `decimation`-th output
+    pub fn new(configs: &[FilterConfig; N], decimation: u16) -> Result<Self, FilterError> {
+        let decimator = Decimator::new(decimation)?;
+        let mut filters = [ChannelFilter::new(); N];
+        for (filter, config) in filters.iter_mut().zip(configs) {
+            filter.set_config(*config)?;
+        }
+        Ok(Self {
+            filters,
+            decimators: [decimator; N],
+        })
+    }
+
+    /// Select a new filter for one channel (0-based index)
+    pub fn set_channel_config(
+        &mut self,
+        index: usize,
+        config: FilterConfig,
+    ) -> Result<(), FilterError> {
+        self.filters
+            .get_mut(index)
+            .ok_or(FilterError::NoSuchChannel)?
+            .set_config(config)
+    }
+
+    /// Clear every channel's filter history, e.g. after a gap in sampling
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    /// Filter a sample set in place
+    ///
+    /// Faulted readings are passed through untouched and clear that channel's
+    /// history, so fault zeros never leak into later outputs. Readings dropped
+    /// by decimation are set to `None`.
+    pub fn process(&mut self, readings: &mut [Option<Reading>; N]) {
+        for ((filter, decimator), slot) in self
+            .filters
+            .iter_mut()
+            .zip(self.decimators.iter_mut())
+            .zip(readings.iter_mut())
+        {
+            let Some(reading) = slot else {
+                continue;
+            };
+            if !reading.is_valid() {
+                filter.reset();
+            } else if !filter.config().is_bypass() {
+                reading.counts = filter.process(reading.counts);
+                reading.quality.insert(QualityFlags::FILTERED);
+            }
+
+            if !decimator.keep() {
+                *slot = None;
+            }
+        }
+    }
+}
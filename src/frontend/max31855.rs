@@ -0,0 +1,77 @@
+//! MAX31855 front end (SPI, read-only, 14-bit output)
+//!
+//! The MAX31855 converts continuously and has no configuration registers.
+//! Each read clocks out a 32-bit frame:
+//! - D31:D18 thermocouple temperature, 0.25°C/LSB
+//! - D16     fault summary
+//! - D15:D4  internal (cold-junction) temperature, 0.0625°C/LSB
+//! - D2/D1/D0 short to VCC / short to GND / open circuit
+
+use embedded_hal::spi::SpiDevice;
+
+use super::ThermocoupleFrontend;
+use crate::max31856::FaultStatus;
+
+const FRAME_FAULT: u32 = 1 << 16;
+const FRAME_SCV: u32 = 1 << 2;
+const FRAME_SCG: u32 = 1 << 1;
+const FRAME_OC: u32 = 1 << 0;
+
+/// MAX31855 on a dedicated chip select
+pub struct Max31855<SPI> {
+    spi: SPI,
+}
+
+impl<SPI> Max31855<SPI>
+where
+    SPI: SpiDevice,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self { spi }
+    }
+
+    /// Release the SPI device
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    fn read_frame(&mut self) -> Result<u32, SPI::Error> {
+        let mut buffer = [0u8; 4];
+        self.spi.read(&mut buffer)?;
+        Ok(u32::from_be_bytes(buffer))
+    }
+}
+
+impl<SPI> ThermocoupleFrontend for Max31855<SPI>
+where
+    SPI: SpiDevice,
+{
+    type Error = SPI::Error;
+
+    fn configure(&mut self) -> Result<(), Self::Error> {
+        // Nothing to configure, but make sure the chip answers
+        self.read_frame().map(|_| ())
+    }
+
+    fn read_temperature(&mut self) -> Result<i32, Self::Error> {
+        // Signed 14-bit at 0.25°C -> 1/128°C
+        let frame = self.read_frame()? as i32;
+        Ok((frame >> 18) * 32)
+    }
+
+    fn read_cold_junction(&mut self) -> Result<i32, Self::Error> {
+        // Signed 12-bit at 0.0625°C -> 1/128°C
+        let frame = self.read_frame()? as i32;
+        Ok(((frame << 16) >> 20) * 8)
+    }
+
+    fn read_faults(&mut self) -> Result<FaultStatus, Self::Error> {
+        let frame = self.read_frame()?;
+        Ok(FaultStatus {
+            tc_range: frame & FRAME_FAULT != 0,
+            ovuv: frame & (FRAME_SCV | FRAME_SCG) != 0,
+            open: frame & FRAME_OC != 0,
+            ..FaultStatus::default()
+        })
+    }
+}
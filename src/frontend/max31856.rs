@@ -0,0 +1,104 @@
+//! MAX31856 front end (SPI, 19-bit linearized output)
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::spi::SpiDevice;
+use embedded_hal_async::digital::Wait;
+
+use super::ThermocoupleFrontend;
+use crate::max31856::timing::SensorTiming;
+use crate::max31856::{self as driver, FaultStatus};
+
+/// MAX31856 with its nFAULT and nDRDY lines
+pub struct Max31856<SPI, FAULT, DRDY> {
+    spi: SPI,
+    nfault: FAULT,
+    ndrdy: DRDY,
+    timing: SensorTiming,
+}
+
+impl<SPI, FAULT, DRDY> Max31856<SPI, FAULT, DRDY>
+where
+    SPI: SpiDevice,
+    FAULT: InputPin,
+    DRDY: InputPin,
+{
+    /// Create a front end using `timing` for filter, averaging and conversion mode
+    pub fn new(spi: SPI, nfault: FAULT, ndrdy: DRDY, timing: SensorTiming) -> Self {
+        Self {
+            spi,
+            nfault,
+            ndrdy,
+            timing,
+        }
+    }
+
+    pub fn timing(&self) -> &SensorTiming {
+        &self.timing
+    }
+
+    /// Use new timing settings from the next `configure`
+    pub fn set_timing(&mut self, timing: SensorTiming) {
+        self.timing = timing;
+    }
+
+    /// Release the SPI device and pins
+    pub fn release(self) -> (SPI, FAULT, DRDY) {
+        (self.spi, self.nfault, self.ndrdy)
+    }
+}
+
+impl<SPI, FAULT, DRDY> ThermocoupleFrontend for Max31856<SPI, FAULT, DRDY>
+where
+    SPI: SpiDevice,
+    FAULT: InputPin,
+    DRDY: InputPin + Wait,
+{
+    type Error = SPI::Error;
+
+    fn configure(&mut self) -> Result<(), Self::Error> {
+        crate::configure_max31856(&mut self.spi, &self.timing)
+    }
+
+    fn read_temperature(&mut self) -> Result<i32, Self::Error> {
+        driver::read_temperature_counts(&mut self.spi)
+    }
+
+    fn read_cold_junction(&mut self) -> Result<i32, Self::Error> {
+        // CJ register LSB is 1/64°C
+        driver::read_cold_junction_counts(&mut self.spi).map(|counts| counts * 2)
+    }
+
+    fn read_faults(&mut self) -> Result<FaultStatus, Self::Error> {
+        driver::read_fault_status(&mut self.spi)
+    }
+
+    fn clear_faults(&mut self) -> Result<(), Self::Error> {
+        driver::clear_faults(&mut self.spi)
+    }
+
+    fn set_cold_junction_offset(&mut self, counts: i32) -> Result<(), Self::Error> {
+        driver::set_cj_temp_offset(
+            &mut self.spi,
+            counts as f32 / super::COUNTS_PER_DEGREE_C as f32,
+        )
+    }
+
+    fn trigger_conversion(&mut self) -> Result<(), Self::Error> {
+        driver::trigger_one_shot(&mut self.spi)
+    }
+
+    async fn wait_ready(&mut self) {
+        // nDRDY is asserted low until the result is read, which every
+        // `read_with_fault_check` does, faulted or not
+        let _ = self.ndrdy.wait_for_low().await;
+    }
+
+    fn log_configuration(&mut self, sensor_num: u8) -> Result<(), Self::Error> {
+        crate::log_max31856_configuration(&mut self.spi, sensor_num)
+    }
+
+    fn read_with_fault_check(&mut self) -> Result<(i32, Option<FaultStatus>), Self::Error> {
+        // Handles the open-circuit reading that arrives before nFAULT latches
+        driver::read_thermocouple_checked(&mut self.spi)
+    }
+}
@@ -0,0 +1,109 @@
+//! MCP9600 front end (I2C, 18-bit ADC, 0.0625°C output)
+
+use embedded_hal::i2c::I2c;
+
+use super::ThermocoupleFrontend;
+use crate::max31856::FaultStatus;
+
+// MCP9600 Register Pointers
+const REG_HOT_JUNCTION: u8 = 0x00; // Hot-Junction Temperature (TH)
+const REG_COLD_JUNCTION: u8 = 0x02; // Cold-Junction Temperature (TC)
+const REG_STATUS: u8 = 0x04; // Status
+const REG_SENSOR_CONFIG: u8 = 0x05; // Thermocouple Sensor Configuration
+const REG_DEVICE_CONFIG: u8 = 0x06; // Device Configuration
+
+// Status Register Bit Definitions
+const STATUS_INPUT_RANGE: u8 = 1 << 4; // Open or shorted thermocouple
+
+// Sensor Configuration Bit Definitions
+pub const SENSOR_TC_TYPE_K: u8 = 0b000 << 4;
+pub const SENSOR_TC_TYPE_J: u8 = 0b001 << 4;
+pub const SENSOR_TC_TYPE_T: u8 = 0b010 << 4;
+pub const SENSOR_TC_TYPE_N: u8 = 0b011 << 4;
+pub const SENSOR_TC_TYPE_S: u8 = 0b100 << 4;
+pub const SENSOR_TC_TYPE_E: u8 = 0b101 << 4;
+pub const SENSOR_TC_TYPE_B: u8 = 0b110 << 4;
+pub const SENSOR_TC_TYPE_R: u8 = 0b111 << 4;
+
+// Device Configuration Bit Definitions
+const DEVICE_ADC_18_BIT: u8 = 0b00 << 5;
+const DEVICE_MODE_NORMAL: u8 = 0b00;
+
+/// Default 7-bit address with ADDR tied to GND
+pub const DEFAULT_ADDRESS: u8 = 0x60;
+
+/// MCP9600 at a fixed I2C address
+pub struct Mcp9600<I2C> {
+    i2c: I2C,
+    address: u8,
+    tc_type: u8,
+}
+
+impl<I2C> Mcp9600<I2C>
+where
+    I2C: I2c,
+{
+    /// Create a front end for a type K thermocouple
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Self {
+            i2c,
+            address,
+            tc_type: SENSOR_TC_TYPE_K,
+        }
+    }
+
+    /// Select the thermocouple type (one of the `SENSOR_TC_TYPE_*` constants)
+    pub fn with_tc_type(mut self, tc_type: u8) -> Self {
+        self.tc_type = tc_type;
+        self
+    }
+
+    /// Release the I2C bus
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn read_register(&mut self, reg: u8) -> Result<[u8; 2], I2C::Error> {
+        let mut buffer = [0u8; 2];
+        self.i2c.write_read(self.address, &[reg], &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl<I2C> ThermocoupleFrontend for Mcp9600<I2C>
+where
+    I2C: I2c,
+{
+    type Error = I2C::Error;
+
+    fn configure(&mut self) -> Result<(), Self::Error> {
+        self.i2c
+            .write(self.address, &[REG_SENSOR_CONFIG, self.tc_type])?;
+        self.i2c.write(
+            self.address,
+            &[REG_DEVICE_CONFIG, DEVICE_ADC_18_BIT | DEVICE_MODE_NORMAL],
+        )
+    }
+
+    fn read_temperature(&mut self) -> Result<i32, Self::Error> {
+        // Signed 16-bit at 0.0625°C -> 1/128°C
+        let raw = i16::from_be_bytes(self.read_register(REG_HOT_JUNCTION)?);
+        Ok(raw as i32 * 8)
+    }
+
+    fn read_cold_junction(&mut self) -> Result<i32, Self::Error> {
+        let raw = i16::from_be_bytes(self.read_register(REG_COLD_JUNCTION)?);
+        Ok(raw as i32 * 8)
+    }
+
+    fn read_faults(&mut self) -> Result<FaultStatus, Self::Error> {
+        let mut status = [0u8; 1];
+        self.i2c
+            .write_read(self.address, &[REG_STATUS], &mut status)?;
+        // The MCP9600 cannot tell an open input from a shorted one
+        Ok(FaultStatus {
+            tc_range: status[0] & STATUS_INPUT_RANGE != 0,
+            ..FaultStatus::default()
+        })
+    }
+}
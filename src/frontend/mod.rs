@@ -0,0 +1,75 @@
+//! Thermocouple front end abstraction
+//!
+//! Every converter reports temperatures in counts of 0.0078125°C (1/128°C),
+//! the native LSB of the MAX31856 linearized TC register. Packets therefore
+//! carry the same units regardless of which chip is fitted to the board.
+
+pub mod max31855;
+pub mod max31856;
+pub mod mcp9600;
+
+pub use max31855::Max31855;
+pub use max31856::Max31856;
+pub use mcp9600::Mcp9600;
+
+use core::future::Future;
+
+use crate::max31856::FaultStatus;
+
+/// Counts per degree Celsius for all front end readings
+pub use thermosoft_protocol::COUNTS_PER_DEGREE_C;
+
+/// A thermocouple-to-digital converter serving a single channel
+pub trait ThermocoupleFrontend {
+    type Error;
+
+    /// Apply the application configuration to the converter
+    fn configure(&mut self) -> Result<(), Self::Error>;
+
+    /// Read the linearized thermocouple temperature in counts
+    fn read_temperature(&mut self) -> Result<i32, Self::Error>;
+
+    /// Read the cold-junction temperature in counts
+    fn read_cold_junction(&mut self) -> Result<i32, Self::Error>;
+
+    /// Read the current fault state
+    fn read_faults(&mut self) -> Result<FaultStatus, Self::Error>;
+
+    /// Clear latched faults (no-op for converters that do not latch)
+    fn clear_faults(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Set the converter's cold-junction offset in counts (no-op for
+    /// converters without one)
+    fn set_cold_junction_offset(&mut self, _counts: i32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Start a single conversion (no-op for free-running converters)
+    fn trigger_conversion(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Wait until a conversion result is available
+    /// Converters without a data-ready signal complete immediately
+    fn wait_ready(&mut self) -> impl Future<Output = ()> {
+        core::future::ready(())
+    }
+
+    /// Read back and log the converter configuration
+    fn log_configuration(&mut self, _sensor_num: u8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Read a temperature, returning 0 and the fault state if a fault is present
+    /// A failed transfer is returned as an error rather than a reading
+    fn read_with_fault_check(&mut self) -> Result<(i32, Option<FaultStatus>), Self::Error> {
+        let status = self.read_faults()?;
+        if status.has_fault() {
+            let _ = self.clear_faults();
+            return Ok((0, Some(status)));
+        }
+        Ok((self.read_temperature()?, None))
+    }
+}
@@ -0,0 +1,196 @@
+//! Hardware interlock outputs driven by the alarm engine
+//!
+//! Each output trips when any of its alarm rules is active, and optionally
+//! when a channel its rules watch reports a sensor fault, so a broken
+//! thermocouple cannot hide an over-temperature. Latching outputs stay
+//! tripped until a manual reset once the cause has gone; auto-reset
+//! outputs release as soon as it has.
+
+use embedded_hal::digital::OutputPin;
+use heapless::Vec;
+
+use crate::acquisition::Reading;
+use crate::alarm::AlarmEngine;
+
+/// Behaviour of one interlock output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterlockConfig {
+    /// Alarm rule ids that trip this output
+    pub rule_ids: &'static [u8],
+    /// Stay tripped until reset, instead of releasing when the cause clears
+    pub latching: bool,
+    /// Trip while a channel watched by `rule_ids` is faulted
+    pub trip_on_fault: bool,
+    /// Drive the pin high when tripped (false drives it low, for lines
+    /// that must be energised to run)
+    pub active_high: bool,
+}
+
+impl InterlockConfig {
+    /// Never trips
+    pub const UNUSED: InterlockConfig = InterlockConfig {
+        rule_ids: &[],
+        latching: false,
+        trip_on_fault: false,
+        active_high: true,
+    };
+
+    /// Pin level while tripped
+    pub const fn tripped_level(&self) -> bool {
+        self.active_high
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InterlockTransition {
+    Tripped,
+    Released,
+}
+
+/// An output changing state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterlockEvent {
+    /// Output index (0-based)
+    pub index: u8,
+    pub transition: InterlockTransition,
+    /// True if a sensor fault, rather than an alarm, caused the trip
+    pub fault: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InterlockError {
+    InvalidIndex,
+    /// The cause is still present, so the output cannot be reset
+    CauseActive,
+}
+
+/// One interlock output pin and its state
+pub struct Interlock<P> {
+    config: InterlockConfig,
+    pin: P,
+    tripped: bool,
+    cause: bool,
+}
+
+impl<P: OutputPin> Interlock<P> {
+    /// Take over `pin`, driving it to the released level
+    pub fn new(config: InterlockConfig, mut pin: P) -> Self {
+        let _ = pin.set_state((!config.tripped_level()).into());
+        Self {
+            config,
+            pin,
+            tripped: false,
+            cause: false,
+        }
+    }
+
+    pub fn config(&self) -> &InterlockConfig {
+        &self.config
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    fn set_tripped(&mut self, tripped: bool) {
+        self.tripped = tripped;
+        let level = if tripped {
+            self.config.tripped_level()
+        } else {
+            !self.config.tripped_level()
+        };
+        let _ = self.pin.set_state(level.into());
+    }
+}
+
+/// Interlock outputs for `N` channels
+pub struct InterlockBank<P, const N: usize, const M: usize> {
+    outputs: [Interlock<P>; M],
+    faulted: [bool; N],
+}
+
+impl<P: OutputPin, const N: usize, const M: usize> InterlockBank<P, N, M> {
+    pub fn new(outputs: [Interlock<P>; M]) -> Self {
+        Self {
+            outputs,
+            faulted: [false; N],
+        }
+    }
+
+    pub fn outputs(&self) -> &[Interlock<P>; M] {
+        &self.outputs
+    }
+
+    /// Update every output from the alarm state and the latest readings
+    /// Channels not sampled this tick keep their last fault status
+    pub fn update(
+        &mut self,
+        alarms: &AlarmEngine<N>,
+        readings: &[Option<Reading>; N],
+    ) -> Vec<InterlockEvent, M> {
+        for (faulted, reading) in self.faulted.iter_mut().zip(readings) {
+            if let Some(reading) = reading {
+                *faulted = !reading.is_valid();
+            }
+        }
+
+        let mut events = Vec::new();
+        for (index, output) in self.outputs.iter_mut().enumerate() {
+            let mut alarm = false;
+            let mut fault = false;
+            for (rule, active) in alarms.rules() {
+                if !output.config.rule_ids.contains(&rule.id) {
+                    continue;
+                }
+                alarm |= active;
+                if output.config.trip_on_fault {
+                    fault |= self.faulted[rule.channel() as usize]
+                        || rule
+                            .reference()
+                            .is_some_and(|reference| self.faulted[reference as usize]);
+                }
+            }
+            output.cause = alarm || fault;
+
+            let transition = if output.cause && !output.tripped {
+                output.set_tripped(true);
+                InterlockTransition::Tripped
+            } else if !output.cause && output.tripped && !output.config.latching {
+                output.set_tripped(false);
+                InterlockTransition::Released
+            } else {
+                continue;
+            };
+            let _ = events.push(InterlockEvent {
+                index: index as u8,
+                transition,
+                fault: fault && !alarm,
+            });
+        }
+        events
+    }
+
+    /// Manually release one latched output
+    pub fn reset(&mut self, index: usize) -> Result<Option<InterlockEvent>, InterlockError> {
+        let output = self
+            .outputs
+            .get_mut(index)
+            .ok_or(InterlockError::InvalidIndex)?;
+        if output.cause {
+            return Err(InterlockError::CauseActive);
+        }
+        if !output.tripped {
+            return Ok(None);
+        }
+        output.set_tripped(false);
+        Ok(Some(InterlockEvent {
+            index: index as u8,
+            transition: InterlockTransition::Released,
+            fault: false,
+        }))
+    }
+}
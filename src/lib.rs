@@ -1,59 +1,364 @@
+#![deny(unsafe_code)]
 #![no_std]
 #![allow(non_snake_case)] // Allow non-snake-case crate name (ThermoSoft-rs)
 
+pub mod acquisition;
+pub mod alarm;
+pub mod blackbox;
+pub mod boot_count;
+pub mod calibration;
+pub mod calibration_session;
+pub mod command;
+pub mod filter;
+pub mod frontend;
+pub mod interlock;
 pub mod max31856;
+pub mod outlier;
+pub mod quality;
+pub mod scheduler;
+pub mod stats;
+pub mod sync;
+pub mod trigger;
 
+use acquisition::{AcquisitionMode, Reading};
+use alarm::{AlarmEvent, AlarmRule, AlarmTransition};
+use calibration::ChannelCalibration;
 use embedded_hal::spi::SpiDevice;
+use filter::FilterConfig;
+use interlock::{InterlockConfig, InterlockEvent, InterlockTransition};
 use max31856::FaultStatus;
 use max31856::registers::*;
+use max31856::timing::{NoiseFilter, OcDetection, SamplePlan, SensorTiming, plan_channel_rates};
+use outlier::OutlierConfig;
+use quality::QualityFlags;
+use scheduler::{ChannelSchedule, SchedulerStats};
+use stats::ChannelStats;
+use sync::{ClockSync, SyncConfig, SyncRole};
+use trigger::{TriggerAction, TriggerConfig};
 
 // Packet batching configuration
 pub const BATCH_SIZE: usize = 10;
 
-/// Packed structure for batched sensor data packet
-/// Matches the C structure layout for network transmission
-/// Also by default the rust compiler will move your fields around
-#[repr(C, packed)]
-#[derive(Clone, Copy)]
-pub struct SensorDataPacket {
-    pub packet_tag: u32,              // Packet identifier
-    pub tc1_temps: [i32; BATCH_SIZE], // Thermocouple 1 temperature batch
-    pub tc2_temps: [i32; BATCH_SIZE], // Thermocouple 2 temperature batch
-    pub tc3_temps: [i32; BATCH_SIZE], // Thermocouple 3 temperature batch
-    pub tc4_temps: [i32; BATCH_SIZE], // Thermocouple 4 temperature batch
-    pub packet_time: u32,             // Timestamp when packet was sent (milliseconds)
+// Wire format of outgoing packets; Legacy keeps the original tag-first layout
+pub const PACKET_FORMAT: PacketFormat = PacketFormat::Versioned;
+
+// Board identifier sent in packet headers; None derives one from the MCU unique ID
+pub const BOARD_ID: Option<u32> = None;
+
+// Number of thermocouple channels on the board variant
+pub const CHANNEL_COUNT: usize = 4;
+
+// Sequential reads, or simultaneous triggered reads for time-aligned data
+pub const ACQUISITION_MODE: AcquisitionMode = AcquisitionMode::Sequential;
+
+// Requested sample rate of each channel in millihertz
+// Every channel's period must be a whole multiple of the fastest channel's
+pub const CHANNEL_SAMPLE_RATES_MHZ: [u32; CHANNEL_COUNT] = [5_000; CHANNEL_COUNT];
+
+// Calibration used when none has been stored in flash
+pub const CHANNEL_CALIBRATIONS: [ChannelCalibration; CHANNEL_COUNT] =
+    [ChannelCalibration::IDENTITY; CHANNEL_COUNT];
+
+// Flash offset of the calibration sector (last 8 KiB sector of the 2 MiB flash)
+// memory.x keeps code out of this sector and the boot counter's
+pub const CALIBRATION_FLASH_OFFSET: u32 = 0x1F_E000;
+
+// Flash offset of the boot counter sector (the sector before the calibration)
+pub const BOOT_COUNT_FLASH_OFFSET: u32 = 0x1F_C000;
+
+// Spike and slew-rate rejection applied to each channel before filtering
+pub const CHANNEL_OUTLIER_CONFIGS: [OutlierConfig; CHANNEL_COUNT] =
+    [OutlierConfig::DISABLED; CHANNEL_COUNT];
+
+// Filter applied to each channel at startup
+pub const CHANNEL_FILTERS: [FilterConfig; CHANNEL_COUNT] = [FilterConfig::BYPASS; CHANNEL_COUNT];
+
+// Keep every Nth filtered sample of each channel (1 = no decimation)
+pub const FILTER_DECIMATION: u16 = 1;
+
+// Full-rate data packets, summary packets, or both
+pub const STREAM_MODE: StreamMode = StreamMode::Full;
+
+// Span of samples each summary packet covers
+pub const SUMMARY_WINDOW: SummaryWindow = SummaryWindow::Batch;
+
+// Threshold alarms evaluated on every full-rate sample set, before filtering (at most MAX_ALARM_RULES)
+// e.g. AlarmRule { id: 1, condition: AlarmCondition::Above { channel: 0, threshold: 200 * 128 }, hysteresis: 2 * 128 }
+pub const ALARM_RULES: &[AlarmRule] = &[];
+
+// Number of interlock outputs on the board variant
+pub const INTERLOCK_COUNT: usize = 2;
+
+// Alarm rules driving each interlock output
+pub const INTERLOCK_CONFIGS: [InterlockConfig; INTERLOCK_COUNT] =
+    [InterlockConfig::UNUSED; INTERLOCK_COUNT];
+
+// External trigger input: polarity, debounce and what it does while active
+pub const TRIGGER_CONFIG: TriggerConfig = TriggerConfig {
+    active_high: true,
+    debounce_us: 1_000,
+    action: TriggerAction::MarkOnly,
+};
+
+// Multi-board synchronisation: this board's role and whether the sync
+// pulse line and/or UDP sync messages are used
+pub const SYNC_CONFIG: SyncConfig = SyncConfig {
+    role: SyncRole::Standalone,
+    pulse: false,
+    network: false,
+};
+
+// Interval between status (telemetry) packets
+pub const STATUS_INTERVAL_MS: u64 = 1_000;
+
+// Per-channel sample rates while a BoostRate trigger is active, in millihertz
+// e.g. [9_000; CHANNEL_COUNT] is the fastest continuous-mode rate with averaging off
+pub const BOOST_SAMPLE_RATES_MHZ: [u32; CHANNEL_COUNT] = CHANNEL_SAMPLE_RATES_MHZ;
+
+// Seconds of full-rate data held by the black-box recorder, and how many
+// of them are recorded after the freeze event
+pub const BLACKBOX_SECONDS: u32 = 60;
+pub const BLACKBOX_POST_TRIGGER_SECONDS: u32 = 10;
+
+// Events that freeze the black-box recorder
+pub const BLACKBOX_FREEZE_ON_ALARM: bool = true;
+pub const BLACKBOX_FREEZE_ON_TRIGGER: bool = true;
+
+/// Black-box records needed for BLACKBOX_SECONDS at the faster of each
+/// channel's normal and boost rates
+pub const BLACKBOX_CAPACITY: usize = {
+    let mut rates_mhz = CHANNEL_SAMPLE_RATES_MHZ;
+    let mut i = 0;
+    while i < CHANNEL_COUNT {
+        if BOOST_SAMPLE_RATES_MHZ[i] > rates_mhz[i] {
+            rates_mhz[i] = BOOST_SAMPLE_RATES_MHZ[i];
+        }
+        i += 1;
+    }
+    blackbox::blackbox_capacity(&rates_mhz, BLACKBOX_SECONDS)
+};
+
+/// Sensor timing for each channel, rejected at compile time if infeasible
+pub const CHANNEL_PLANS: [SamplePlan; CHANNEL_COUNT] = match plan_channel_rates(
+    &CHANNEL_SAMPLE_RATES_MHZ,
+    NoiseFilter::Hz60,
+    ACQUISITION_MODE.conversion_mode(),
+    OcDetection::RsLt5k,
+) {
+    Ok(plans) => plans,
+    Err(_) => panic!("CHANNEL_SAMPLE_RATES_MHZ is not achievable with the current sensor settings"),
+};
+
+/// Which channels are sampled on each tick of the fastest channel's period
+pub const CHANNEL_SCHEDULE: ChannelSchedule<CHANNEL_COUNT> =
+    ChannelSchedule::from_plans(&CHANNEL_PLANS);
+
+/// Sensor timing for each channel while boosted, rejected at compile time if infeasible
+pub const BOOST_PLANS: [SamplePlan; CHANNEL_COUNT] = match plan_channel_rates(
+    &BOOST_SAMPLE_RATES_MHZ,
+    NoiseFilter::Hz60,
+    ACQUISITION_MODE.conversion_mode(),
+    OcDetection::RsLt5k,
+) {
+    Ok(plans) => plans,
+    Err(_) => panic!("BOOST_SAMPLE_RATES_MHZ is not achievable with the current sensor settings"),
+};
+
+/// Channel schedule while boosted
+pub const BOOST_SCHEDULE: ChannelSchedule<CHANNEL_COUNT> =
+    ChannelSchedule::from_plans(&BOOST_PLANS);
+
+pub use thermosoft_protocol::{
+    CalibrationInfo, ChannelSummary, EVENT_ALARM_CLEARED, EVENT_ALARM_RAISED,
+    EVENT_INTERLOCK_RELEASED, EVENT_INTERLOCK_TRIPPED, EVENT_TRIGGER_ASSERTED,
+    EVENT_TRIGGER_RELEASED, EventPacket, PACKET_CRC_LEN, PACKET_MAGIC, PACKET_TAG_DATA,
+    PACKET_TAG_EVENT, PACKET_TAG_STATUS, PACKET_TAG_SUMMARY, PACKET_TAG_SYNC, PROTOCOL_VERSION,
+    PacketFormat, PacketHeader, PacketSource, Sample, verify_packet,
+};
+pub use thermosoft_protocol::{crc, wire};
+
+/// Batched sensor data packet for this board's channels
+pub type SensorDataPacket = thermosoft_protocol::SensorDataPacket<CHANNEL_COUNT, BATCH_SIZE>;
+/// Summary statistics packet for this board's channels
+pub type SummaryPacket = thermosoft_protocol::SummaryPacket<CHANNEL_COUNT>;
+/// Telemetry packet for this board's channels
+pub type StatusPacket = thermosoft_protocol::StatusPacket<CHANNEL_COUNT>;
+
+/// Longest encoded payload, packet tag included
+pub const MAX_PAYLOAD_LEN: usize = {
+    let sizes = [
+        SensorDataPacket::ENCODED_LEN,
+        SummaryPacket::ENCODED_LEN,
+        EventPacket::ENCODED_LEN,
+        StatusPacket::ENCODED_LEN,
+    ];
+    let mut max = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        if sizes[i] > max {
+            max = sizes[i];
+        }
+        i += 1;
+    }
+    max
+};
+
+/// Largest packet the firmware sends, in either format
+/// The header replaces the 4-byte packet tag
+pub const MAX_PACKET_LEN: usize = MAX_PAYLOAD_LEN + PacketHeader::LEN - 4 + PACKET_CRC_LEN;
+
+/// Write a tagged packet into `buffer` in the given format, returning its length
+/// `packet` is a payload from `encode_into`, packet tag first
+pub fn frame_packet(
+    format: PacketFormat,
+    source: &PacketSource,
+    sequence: u32,
+    packet: &[u8],
+    buffer: &mut [u8],
+) -> usize {
+    thermosoft_protocol::frame_packet::<CHANNEL_COUNT, BATCH_SIZE>(
+        format, source, sequence, packet, buffer,
+    )
 }
 
-impl Default for SensorDataPacket {
-    fn default() -> Self {
-        Self::new()
+/// Which packets are streamed to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StreamMode {
+    /// Every sample, in `SensorDataPacket`s
+    Full,
+    /// Only `SummaryPacket`s
+    Summary,
+    /// Both, summaries alongside the full-rate data
+    Both,
+}
+
+impl StreamMode {
+    pub const fn sends_data(self) -> bool {
+        matches!(self, StreamMode::Full | StreamMode::Both)
+    }
+
+    pub const fn sends_summary(self) -> bool {
+        matches!(self, StreamMode::Summary | StreamMode::Both)
     }
 }
 
-impl SensorDataPacket {
-    /// Create a new empty packet
-    pub const fn new() -> Self {
+/// Window over which summary statistics are computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SummaryWindow {
+    /// The samples of each data batch
+    Batch,
+    /// A fixed span of time, in milliseconds
+    Millis(u32),
+}
+
+impl From<Reading> for Sample {
+    fn from(reading: Reading) -> Self {
         Self {
-            packet_tag: 0,
-            tc1_temps: [0; BATCH_SIZE],
-            tc2_temps: [0; BATCH_SIZE],
-            tc3_temps: [0; BATCH_SIZE],
-            tc4_temps: [0; BATCH_SIZE],
-            packet_time: 0,
+            counts: reading.counts,
+            timestamp_us: reading.timestamp_us,
+            quality: reading.quality,
         }
     }
+}
 
-    /// Convert packet to byte slice for transmission
-    pub fn as_bytes(&self) -> &[u8] {
-        unsafe {
-            core::slice::from_raw_parts(
-                self as *const Self as *const u8,
-                core::mem::size_of::<Self>(),
-            )
+impl From<ChannelStats> for ChannelSummary {
+    fn from(stats: ChannelStats) -> Self {
+        Self {
+            count: stats.count,
+            min: stats.min,
+            max: stats.max,
+            mean: stats.mean,
+            stddev: stats.stddev,
         }
     }
 }
 
+impl From<AlarmEvent> for EventPacket {
+    fn from(event: AlarmEvent) -> Self {
+        let event_kind = match event.transition {
+            AlarmTransition::Raised => EVENT_ALARM_RAISED,
+            AlarmTransition::Cleared => EVENT_ALARM_CLEARED,
+        };
+        EventPacket::new(
+            event_kind,
+            event.rule_id,
+            event.channel,
+            event.value,
+            event.timestamp_us,
+        )
+    }
+}
+
+/// Packet for an interlock output changing state at `timestamp_us`
+pub fn interlock_event_packet(event: InterlockEvent, timestamp_us: u64) -> EventPacket {
+    let event_kind = match event.transition {
+        InterlockTransition::Tripped => EVENT_INTERLOCK_TRIPPED,
+        InterlockTransition::Released => EVENT_INTERLOCK_RELEASED,
+    };
+    EventPacket::new(
+        event_kind,
+        event.index,
+        0xFF,
+        event.fault as i32,
+        timestamp_us,
+    )
+}
+
+/// ID and date of a channel's calibration, as reported in status packets
+fn calibration_info(calibration: &ChannelCalibration) -> CalibrationInfo {
+    CalibrationInfo {
+        id: calibration.id,
+        date: calibration.date,
+    }
+}
+
+/// Counters of packets that never reached the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkStats {
+    pub send_errors: u32,
+    pub send_timeouts: u32,
+    pub queue_drops: u32,
+}
+
+/// Telemetry packet from the current clock, scheduler and link state
+pub fn status_packet(
+    clock: &ClockSync,
+    scheduler: &SchedulerStats,
+    link: &LinkStats,
+    calibrations: &[ChannelCalibration; CHANNEL_COUNT],
+    local_us: u64,
+) -> StatusPacket {
+    StatusPacket {
+        packet_tag: PACKET_TAG_STATUS,
+        sync_role: match SYNC_CONFIG.role {
+            SyncRole::Standalone => 0,
+            SyncRole::Master => 1,
+            SyncRole::Follower => 2,
+        },
+        sync_source: clock.source() as u8,
+        sync_holdover: (SYNC_CONFIG.role == SyncRole::Follower && clock.in_holdover(local_us))
+            as u8,
+        reserved: 0,
+        sync_offset_us: clock.offset_us(),
+        sync_error_us: clock
+            .last_error_us()
+            .clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+        sync_age_ms: clock.last_update_us().map_or(u32::MAX, |last| {
+            (local_us.saturating_sub(last) / 1000).min(u32::MAX as u64) as u32
+        }),
+        overruns: scheduler.overruns,
+        max_jitter_us: scheduler.max_jitter_us,
+        send_errors: link.send_errors,
+        send_timeouts: link.send_timeouts,
+        queue_drops: link.queue_drops,
+        calibrations: calibrations.each_ref().map(calibration_info),
+        packet_time: 0,
+    }
+}
+
 /// Log faults for a sensor
 #[cfg(feature = "defmt")]
 pub fn log_faults(sensor_num: u8, faults: &FaultStatus) {
@@ -89,20 +394,37 @@ pub fn log_faults(_sensor_num: u8, _faults: &FaultStatus) {
     // No-op without defmt
 }
 
+/// Log outlier decisions for a sensor
+#[cfg(feature = "defmt")]
+pub fn log_quality(sensor_num: u8, quality: QualityFlags) {
+    if quality.contains(QualityFlags::SPIKE) {
+        defmt::warn!("Sensor {} - Spike rejected", sensor_num);
+    }
+    if quality.contains(QualityFlags::SLEW) {
+        defmt::warn!("Sensor {} - Slew rate exceeded", sensor_num);
+    }
+    if quality.contains(QualityFlags::SPI_ERROR) {
+        defmt::warn!("Sensor {} - Read failed", sensor_num);
+    }
+}
+
+/// Log outlier decisions for a sensor (no-op when defmt is disabled)
+#[cfg(not(feature = "defmt"))]
+pub fn log_quality(_sensor_num: u8, _quality: QualityFlags) {
+    // No-op without defmt
+}
+
 /// Configure MAX31856 with application-specific settings
-pub fn configure_max31856<SPI>(spi: &mut SPI) -> Result<(), SPI::Error>
+/// Filter, OC detection, conversion mode and averaging come from `timing`
+pub fn configure_max31856<SPI>(spi: &mut SPI, timing: &SensorTiming) -> Result<(), SPI::Error>
 where
     SPI: SpiDevice,
 {
-    let cr0_config = CR0_FILTER_60HZ
-        | CR0_FAULT_INTERRUPT
-        | CR0_CJ_ENABLED
-        | CR0_OC_ENABLED_RS_LT_5K
-        | CR0_CONV_CONTINUOUS;
+    let cr0_config = timing.cr0_bits() | CR0_FAULT_INTERRUPT | CR0_CJ_ENABLED;
 
     spi.write(&[CR0_WRITE, cr0_config])?;
 
-    let cr1_config = CR1_TC_TYPE_K | CR1_AVG_4_SAMPLES;
+    let cr1_config = CR1_TC_TYPE_K | timing.cr1_bits();
 
     spi.write(&[CR1_WRITE, cr1_config])?;
 
@@ -121,14 +443,26 @@ where
 }
 
 /// Configure and verify a MAX31856 sensor with detailed logging
-pub fn configure_and_verify_max31856<SPI>(spi: &mut SPI, sensor_num: u8) -> Result<(), SPI::Error>
+pub fn configure_and_verify_max31856<SPI>(
+    spi: &mut SPI,
+    timing: &SensorTiming,
+    sensor_num: u8,
+) -> Result<(), SPI::Error>
 where
     SPI: SpiDevice,
 {
     // Configure the sensor
-    configure_max31856(spi)?;
+    configure_max31856(spi, timing)?;
 
     // Read back and verify configuration
+    log_max31856_configuration(spi, sensor_num)
+}
+
+/// Read back and log the MAX31856 configuration registers
+pub fn log_max31856_configuration<SPI>(spi: &mut SPI, sensor_num: u8) -> Result<(), SPI::Error>
+where
+    SPI: SpiDevice,
+{
     #[cfg(feature = "defmt")]
     {
         let regs = max31856::read_all_config_registers(spi)?;
@@ -157,9 +491,9 @@ where
         );
     }
 
-    // Suppress unused variable warning when defmt is disabled
+    // Suppress unused variable warnings when defmt is disabled
     #[cfg(not(feature = "defmt"))]
-    let _ = sensor_num;
+    let _ = (spi, sensor_num);
 
     Ok(())
 }
@@ -9,7 +9,33 @@ use panic_halt as _;
 #[cfg(feature = "defmt")]
 use {defmt_rtt as _, panic_probe as _};
 
-use ThermoSoft_rs::{BATCH_SIZE, SensorDataPacket, log_faults, max31856};
+use ThermoSoft_rs::acquisition::{Acquisition, AcquisitionMode, Reading};
+use ThermoSoft_rs::alarm::{AlarmEngine, AlarmTransition};
+use ThermoSoft_rs::blackbox::{BLACKBOX_RECORD_LEN, BlackBox, FreezeReason};
+use ThermoSoft_rs::boot_count::increment_boot_count;
+use ThermoSoft_rs::calibration::CalibrationBank;
+use ThermoSoft_rs::calibration_session::{CalibrationSession, SessionError};
+use ThermoSoft_rs::command::{Command, CommandError, CommandResult, ReplyText, format_reply};
+use ThermoSoft_rs::crc::crc32;
+use ThermoSoft_rs::filter::{FilterBank, FilterError, FilterPreset};
+use ThermoSoft_rs::frontend::{Max31856, ThermocoupleFrontend};
+use ThermoSoft_rs::interlock::{Interlock, InterlockBank, InterlockError};
+use ThermoSoft_rs::outlier::OutlierBank;
+use ThermoSoft_rs::quality::QualityFlags;
+use ThermoSoft_rs::scheduler::SampleScheduler;
+use ThermoSoft_rs::stats::StatsBank;
+use ThermoSoft_rs::sync::{ClockSync, SYNC_PERIOD_US, SyncMessage, SyncRole};
+use ThermoSoft_rs::wire::PacketError;
+use ThermoSoft_rs::{
+    ACQUISITION_MODE, ALARM_RULES, BLACKBOX_CAPACITY, BLACKBOX_FREEZE_ON_ALARM,
+    BLACKBOX_FREEZE_ON_TRIGGER, BLACKBOX_POST_TRIGGER_SECONDS, BOARD_ID, BOOST_PLANS,
+    BOOST_SCHEDULE, BOOT_COUNT_FLASH_OFFSET, CALIBRATION_FLASH_OFFSET, CHANNEL_CALIBRATIONS,
+    CHANNEL_COUNT, CHANNEL_FILTERS, CHANNEL_OUTLIER_CONFIGS, CHANNEL_PLANS, CHANNEL_SCHEDULE,
+    ChannelSummary, EventPacket, FILTER_DECIMATION, INTERLOCK_CONFIGS, INTERLOCK_COUNT, LinkStats,
+    MAX_PACKET_LEN, MAX_PAYLOAD_LEN, PACKET_FORMAT, PacketSource, STATUS_INTERVAL_MS, STREAM_MODE,
+    SUMMARY_WINDOW, SYNC_CONFIG, SensorDataPacket, StatusPacket, SummaryPacket, SummaryWindow,
+    TRIGGER_CONFIG, frame_packet, interlock_event_packet, log_faults, log_quality, status_packet,
+};
 
 // Conditional logging macro - uses defmt when available, no-op otherwise
 #[cfg(feature = "defmt")]
@@ -25,10 +51,13 @@ macro_rules! info {
 use embassy_executor::Spawner;
 use embassy_futures::select::{Either, select};
 use embassy_net::{
-    Ipv4Address, Ipv4Cidr, StackResources,
+    Ipv4Address, Ipv4Cidr, Stack, StackResources,
+    tcp::{self, TcpSocket},
     udp::{PacketMetadata, UdpSocket},
 };
 use embassy_stm32::eth::{Ethernet, GenericPhy, PacketQueue};
+use embassy_stm32::exti::ExtiInput;
+use embassy_stm32::flash::Flash;
 use embassy_stm32::gpio::{Input, Level, Output, Pull, Speed};
 use embassy_stm32::peripherals::ETH;
 use embassy_stm32::rcc::{
@@ -39,12 +68,246 @@ use embassy_stm32::rng::Rng;
 use embassy_stm32::spi::{MODE_1, Spi};
 use embassy_stm32::time::Hertz;
 use embassy_stm32::{Config, bind_interrupts, eth, peripherals, rng};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
 use embassy_time::Duration;
+use embassy_time::Instant;
 use embassy_time::Timer;
+use embedded_storage::nor_flash::NorFlash;
+
+use core::fmt::Write;
 
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use heapless::Vec;
 use static_cell::StaticCell;
 
+// Longest conversion of any channel, boosted or not
+const MAX_CONVERSION_TIME_US: u32 =
+    if BOOST_SCHEDULE.max_conversion_time_us > CHANNEL_SCHEDULE.max_conversion_time_us {
+        BOOST_SCHEDULE.max_conversion_time_us
+    } else {
+        CHANNEL_SCHEDULE.max_conversion_time_us
+    };
+
+// Longest wait for DRDY in simultaneous mode before reading anyway
+const DRDY_TIMEOUT: Duration = Duration::from_micros(MAX_CONVERSION_TIME_US as u64 + 50_000);
+
+// Clock offset to the multi-board timebase, corrected by the sync tasks
+static CLOCK: Mutex<CriticalSectionRawMutex, RefCell<ClockSync>> =
+    Mutex::new(RefCell::new(ClockSync::new()));
+
+// Sync messages are broadcast on the local subnet
+const SYNC_PORT: u16 = 1686;
+const SYNC_BROADCAST: Ipv4Address = Ipv4Address::new(192, 168, 88, 255);
+
+// Length of each sync pulse
+const SYNC_PULSE_WIDTH: Duration = Duration::from_millis(10);
+
+// Recent full-rate readings, frozen around alarms and triggers
+static BLACK_BOX: Mutex<CriticalSectionRawMutex, RefCell<BlackBox<BLACKBOX_CAPACITY>>> =
+    Mutex::new(RefCell::new(BlackBox::new(
+        BLACKBOX_POST_TRIGGER_SECONDS as u64 * 1_000_000,
+    )));
+
+// TCP port serving black-box downloads
+const BLACKBOX_PORT: u16 = 1687;
+
+// Trigger input state, written by the trigger task
+static TRIGGER_ACTIVE: AtomicBool = AtomicBool::new(false);
+// Set by the trigger task on every accepted edge, cleared by the sampling loop
+static TRIGGER_EDGE: AtomicBool = AtomicBool::new(false);
+
+// Packets lost before reaching the network, reported in status packets
+static SEND_ERRORS: AtomicU32 = AtomicU32::new(0);
+static SEND_TIMEOUTS: AtomicU32 = AtomicU32::new(0);
+static QUEUE_DROPS: AtomicU32 = AtomicU32::new(0);
+
+// Completed packets waiting for the UDP task
+static PACKET_CHANNEL: Channel<CriticalSectionRawMutex, OutgoingPacket, 4> = Channel::new();
+
+// Alarm events, sent ahead of any queued batches
+static EVENT_CHANNEL: Channel<CriticalSectionRawMutex, EventPacket, 16> = Channel::new();
+
+// Commands from the network, handled between samples, and their results
+static COMMAND_CHANNEL: Channel<CriticalSectionRawMutex, Command, 1> = Channel::new();
+static REPLY_CHANNEL: Channel<CriticalSectionRawMutex, CommandResult, 1> = Channel::new();
+
+// UDP port the board listens on for commands
+const COMMAND_PORT: u16 = 1685;
+
+// Longest wait for the sampling loop to run a command
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Interlocks = InterlockBank<Output<'static>, CHANNEL_COUNT, INTERLOCK_COUNT>;
+
+/// Any packet the UDP task can send
+/// No allocator, so variants are stored inline in the static queue
+#[allow(clippy::large_enum_variant)]
+enum OutgoingPacket {
+    Data(SensorDataPacket),
+    Summary(SummaryPacket),
+    Event(EventPacket),
+    Status(StatusPacket),
+}
+
+impl OutgoingPacket {
+    fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, PacketError> {
+        match self {
+            OutgoingPacket::Data(packet) => packet.encode_into(buffer),
+            OutgoingPacket::Summary(packet) => packet.encode_into(buffer),
+            OutgoingPacket::Event(packet) => packet.encode_into(buffer),
+            OutgoingPacket::Status(packet) => packet.encode_into(buffer),
+        }
+    }
+}
+
+/// Current time on the shared timebase, in microseconds
+fn synced_now_us() -> u64 {
+    let local_us = Instant::now().as_micros();
+    CLOCK.lock(|clock| clock.borrow().to_synced(local_us))
+}
+
+/// Queue a packet for the UDP task, dropping it if the queue is full
+fn queue_packet(packet: OutgoingPacket) {
+    if PACKET_CHANNEL.try_send(packet).is_err() {
+        QUEUE_DROPS.fetch_add(1, Ordering::Relaxed);
+        info!("UDP queue full - packet dropped");
+    }
+}
+
+/// Queue an event for the UDP task, dropping it if the queue is full
+fn queue_event(event: EventPacket) {
+    if EVENT_CHANNEL.try_send(event).is_err() {
+        QUEUE_DROPS.fetch_add(1, Ordering::Relaxed);
+        info!("Event queue full - event dropped");
+    }
+}
+
+/// Snapshot of the lost-packet counters
+fn link_stats() -> LinkStats {
+    LinkStats {
+        send_errors: SEND_ERRORS.load(Ordering::Relaxed),
+        send_timeouts: SEND_TIMEOUTS.load(Ordering::Relaxed),
+        queue_drops: QUEUE_DROPS.load(Ordering::Relaxed),
+    }
+}
+
+/// Set every converter's cold-junction offset from its calibration
+fn apply_cold_junction_offsets<F: ThermocoupleFrontend>(
+    acquisition: &mut Acquisition<F, CHANNEL_COUNT>,
+    calibration: &CalibrationBank<CHANNEL_COUNT>,
+) {
+    for (channel, calibration) in acquisition
+        .channels_mut()
+        .iter_mut()
+        .zip(calibration.channels())
+    {
+        info!(
+            "Sensor {} - Calibration {} ({})",
+            channel.number, calibration.id, calibration.date
+        );
+        if channel
+            .frontend
+            .set_cold_junction_offset(calibration.cj_offset_counts)
+            .is_err()
+        {
+            info!("Sensor {} - Failed to set CJ offset", channel.number);
+        }
+    }
+}
+
+/// Release one or all latched interlock outputs
+fn reset_interlocks(interlocks: &mut Interlocks, index: Option<u8>) -> CommandResult {
+    let indices = match index {
+        Some(index) => index as usize..index as usize + 1,
+        None => 0..INTERLOCK_COUNT,
+    };
+    let mut result = Ok(ReplyText::new());
+    for index in indices {
+        match interlocks.reset(index) {
+            Ok(Some(event)) => {
+                info!("Interlock {} reset", index);
+                queue_event(interlock_event_packet(event, synced_now_us()));
+            }
+            Ok(None) => {}
+            Err(InterlockError::InvalidIndex) => {
+                result = Err(CommandError::Rejected("no such interlock"))
+            }
+            Err(InterlockError::CauseActive) => {
+                result = Err(CommandError::Rejected("cause still active"))
+            }
+        }
+    }
+    result
+}
+
+/// Switch one channel to a preset filter
+fn select_filter(
+    filter_bank: &mut FilterBank<CHANNEL_COUNT>,
+    channel: u8,
+    preset: FilterPreset,
+) -> CommandResult {
+    match filter_bank.set_channel_config(channel as usize, preset.config()) {
+        Ok(()) => {
+            info!("Channel {} filter: {:?}", channel, preset);
+            Ok(ReplyText::new())
+        }
+        Err(FilterError::NoSuchChannel) => Err(CommandError::Rejected("no such channel")),
+        Err(_) => Err(CommandError::Rejected("invalid filter")),
+    }
+}
+
+/// Run a calibration command, storing a committed calibration in flash
+fn run_calibration_command(
+    command: Command,
+    session: &mut CalibrationSession<CHANNEL_COUNT>,
+    calibration: &mut CalibrationBank<CHANNEL_COUNT>,
+    flash: &mut impl NorFlash,
+) -> CommandResult {
+    let rejected = |error: SessionError| CommandError::Rejected(error.message());
+    let mut reply = ReplyText::new();
+    match command {
+        Command::CalibrationStart { channel, samples } => {
+            session.start(channel as usize, samples).map_err(rejected)?;
+            let _ = session.write_status(&mut reply);
+        }
+        Command::CalibrationPoint { reference_counts } => {
+            session.capture(reference_counts).map_err(rejected)?;
+            let _ = session.write_status(&mut reply);
+        }
+        Command::CalibrationFit(kind) => {
+            let fit = session.fit(kind).map_err(rejected)?;
+            let _ = write!(
+                reply,
+                "gain {} offset {} residual {}",
+                fit.gain, fit.offset_counts, fit.max_residual_counts
+            );
+        }
+        Command::CalibrationStatus => {
+            let _ = session.write_status(&mut reply);
+        }
+        Command::CalibrationCommit { id, date } => {
+            let (channel, record) = session
+                .result(id, date, calibration.channels())
+                .map_err(rejected)?;
+            // Only replace the calibration in use once it is safely stored
+            let mut updated = *calibration;
+            updated
+                .set_channel(channel, record)
+                .and_then(|()| updated.save(flash, CALIBRATION_FLASH_OFFSET))
+                .map_err(|error| CommandError::Rejected(error.message()))?;
+            *calibration = updated;
+            session.finish();
+            info!("Sensor {} - Calibration {} stored", channel + 1, id);
+        }
+        Command::CalibrationAbort => session.abort(),
+        _ => return Err(CommandError::Unknown),
+    }
+    Ok(reply)
+}
+
 bind_interrupts!(struct Irqs {
     ETH => eth::InterruptHandler;
     RNG => rng::InterruptHandler<peripherals::RNG>;
@@ -118,8 +381,8 @@ async fn main(spawner: Spawner) -> ! {
 
     // Status LEDs
     let mut link_status_led = Output::new(p.PC7, Level::Low, Speed::Low);
-    let mut data_send_led = Output::new(p.PC8, Level::Low, Speed::Low);
-    let mut send_error_led = Output::new(p.PC9, Level::Low, Speed::Low);
+    let data_send_led = Output::new(p.PC8, Level::Low, Speed::Low);
+    let send_error_led = Output::new(p.PC9, Level::Low, Speed::Low);
 
     static PACKETS: StaticCell<PacketQueue<16, 16>> = StaticCell::new();
     let device = Ethernet::new(
@@ -184,43 +447,434 @@ async fn main(spawner: Spawner) -> ! {
     let spi = Spi::new_blocking(p.SPI1, p.PB3, p.PB5, p.PB4, spi_config);
 
     // Create shared SPI bus using RefCell for blocking SPI
-    use core::cell::RefCell;
     use embedded_hal_bus::spi::RefCellDevice;
 
     let spi_bus = RefCell::new(spi);
 
-    let cs1 = Output::new(p.PA15, Level::High, Speed::VeryHigh); // CS1
-    let cs2 = Output::new(p.PC12, Level::High, Speed::VeryHigh); // CS2
-    let cs3 = Output::new(p.PC14, Level::High, Speed::VeryHigh); // CS3
-    let cs4 = Output::new(p.PC2, Level::High, Speed::VeryHigh); // CS4
+    // Board pin map - one entry per channel, CHANNEL_COUNT entries each
+    let cs_pins: [Output; CHANNEL_COUNT] = [
+        Output::new(p.PA15, Level::High, Speed::VeryHigh), // CS1
+        Output::new(p.PC12, Level::High, Speed::VeryHigh), // CS2
+        Output::new(p.PC14, Level::High, Speed::VeryHigh), // CS3
+        Output::new(p.PC2, Level::High, Speed::VeryHigh),  // CS4
+    ];
+
+    let nfault_pins: [Input; CHANNEL_COUNT] = [
+        Input::new(p.PA10, Pull::Up), // NFAULT1
+        Input::new(p.PC11, Pull::Up), // NFAULT2
+        Input::new(p.PC13, Pull::Up), // NFAULT3
+        Input::new(p.PC0, Pull::Up),  // NFAULT4
+    ];
+
+    // DRDY lines use EXTI so simultaneous mode can await them concurrently
+    let ndrdy_pins: [ExtiInput; CHANNEL_COUNT] = [
+        ExtiInput::new(p.PA9, p.EXTI9, Pull::Up),   // DRDY1
+        ExtiInput::new(p.PA8, p.EXTI8, Pull::Up),   // DRDY2
+        ExtiInput::new(p.PC15, p.EXTI15, Pull::Up), // DRDY3
+        ExtiInput::new(p.PC3, p.EXTI3, Pull::Up),   // DRDY4
+    ];
 
-    let mut nfault1 = Input::new(p.PA10, Pull::Up); // NFAULT1
-    let mut nfault2 = Input::new(p.PC11, Pull::Up); // NFAULT2
-    let mut nfault3 = Input::new(p.PC13, Pull::Up); // NFAULT3
-    let mut nfault4 = Input::new(p.PC0, Pull::Up); // NFAULT4
+    // Interlock outputs (relay or abort lines), released until an alarm trips them
+    let released = |index: usize| Level::from(!INTERLOCK_CONFIGS[index].tripped_level());
+    let interlock_pins: [Output; INTERLOCK_COUNT] = [
+        Output::new(p.PB0, released(0), Speed::Low), // INTERLOCK1
+        Output::new(p.PB1, released(1), Speed::Low), // INTERLOCK2
+    ];
+    let mut interlock_pins = interlock_pins.into_iter();
+    let mut interlocks: Interlocks = InterlockBank::new(core::array::from_fn(|index| {
+        Interlock::new(INTERLOCK_CONFIGS[index], interlock_pins.next().unwrap())
+    }));
 
-    let mut ndrdy1 = Input::new(p.PA9, Pull::Up); // DRDY1
-    let mut ndrdy2 = Input::new(p.PA8, Pull::Up); // DRDY2
-    let mut ndrdy3 = Input::new(p.PC15, Pull::Up); // DRDY3
-    let mut ndrdy4 = Input::new(p.PC3, Pull::Up); // DRDY4
+    // External trigger input, e.g. an ignition signal
+    let trigger_pin = ExtiInput::new(p.PB6, p.EXTI6, Pull::Down); // TRIGGER
 
-    // Create SPI devices using RefCellDevice
-    let mut spi_dev1 = RefCellDevice::new(&spi_bus, cs1, embassy_time::Delay).unwrap();
-    let mut spi_dev2 = RefCellDevice::new(&spi_bus, cs2, embassy_time::Delay).unwrap();
-    let mut spi_dev3 = RefCellDevice::new(&spi_bus, cs3, embassy_time::Delay).unwrap();
-    let mut spi_dev4 = RefCellDevice::new(&spi_bus, cs4, embassy_time::Delay).unwrap();
+    // Sync pulse line, driven by a master and followed by the other boards
+    match (SYNC_CONFIG.role, SYNC_CONFIG.pulse) {
+        (SyncRole::Master, _) => {
+            CLOCK.lock(|clock| *clock.borrow_mut() = ClockSync::master());
+            let pulse = SYNC_CONFIG
+                .pulse
+                .then(|| Output::new(p.PB7, Level::Low, Speed::Low)); // SYNC
+            spawner
+                .spawn(sync_master_task(stack, pulse))
+                .expect("Sync task failed to spawn.");
+        }
+        (SyncRole::Follower, true) => {
+            let pulse = ExtiInput::new(p.PB7, p.EXTI7, Pull::Down); // SYNC
+            spawner
+                .spawn(sync_pulse_task(pulse))
+                .expect("Sync pulse task failed to spawn.");
+        }
+        _ => {}
+    }
+    if SYNC_CONFIG.role == SyncRole::Follower && SYNC_CONFIG.network {
+        spawner
+            .spawn(sync_listen_task(stack))
+            .expect("Sync listen task failed to spawn.");
+    }
+
+    // Thermocouple front ends on RefCellDevice SPI devices
+    // Swap the front end type here for other converter chips
+    let mut cs_pins = cs_pins.into_iter();
+    let mut nfault_pins = nfault_pins.into_iter();
+    let mut ndrdy_pins = ndrdy_pins.into_iter();
+    let frontends: [_; CHANNEL_COUNT] = core::array::from_fn(|channel| {
+        let spi_dev =
+            RefCellDevice::new(&spi_bus, cs_pins.next().unwrap(), embassy_time::Delay).unwrap();
+        Max31856::new(
+            spi_dev,
+            nfault_pins.next().unwrap(),
+            ndrdy_pins.next().unwrap(),
+            CHANNEL_PLANS[channel].timing,
+        )
+    });
+    let mut acquisition = Acquisition::new(frontends);
 
     // Configure all sensors with verification
+    #[cfg(feature = "defmt")]
+    for (channel, plan) in CHANNEL_PLANS.iter().enumerate() {
+        info!(
+            "Sensor {} - Sample plan: {:?} ({} us per conversion, {} us period)",
+            channel + 1,
+            plan.timing,
+            plan.conversion_time_us,
+            plan.sample_period_us
+        );
+    }
     info!("Configuring and verifying all sensors...");
-    ThermoSoft_rs::configure_and_verify_max31856(&mut spi_dev1, 1)
-        .expect("Failed to configure sensor 1");
-    ThermoSoft_rs::configure_and_verify_max31856(&mut spi_dev2, 2)
-        .expect("Failed to configure sensor 2");
-    ThermoSoft_rs::configure_and_verify_max31856(&mut spi_dev3, 3)
-        .expect("Failed to configure sensor 3");
-    ThermoSoft_rs::configure_and_verify_max31856(&mut spi_dev4, 4)
-        .expect("Failed to configure sensor 4");
+    if let Err((sensor_num, _)) = acquisition.configure() {
+        panic!("Failed to configure sensor {}", sensor_num);
+    }
+
+    // Stored calibration, falling back to the built-in one
+    let mut flash = Flash::new_blocking(p.FLASH);
+    let mut calibration = match CalibrationBank::load(&mut flash, CALIBRATION_FLASH_OFFSET) {
+        Ok(calibration) => calibration,
+        Err(_e) => {
+            info!("No stored calibration ({:?}), using defaults", _e);
+            CalibrationBank::new(CHANNEL_CALIBRATIONS).expect("Invalid default calibration")
+        }
+    };
+    apply_cold_junction_offsets(&mut acquisition, &calibration);
+    let mut calibration_session = CalibrationSession::<CHANNEL_COUNT>::new();
+
+    // Packets are sent from their own task so network delays never hold up sampling
+    // Identify this board and this run in packet headers
+    let board_id = BOARD_ID.unwrap_or_else(|| crc32(embassy_stm32::uid::uid()));
+    let epoch = increment_boot_count(&mut flash, BOOT_COUNT_FLASH_OFFSET)
+        .inspect_err(|_e| {
+            info!("Failed to store boot count: {:?}", _e);
+        })
+        .unwrap_or(0);
+    info!("Board ID: {:08x}, boot {}", board_id, epoch);
+    let source = PacketSource { board_id, epoch };
+
+    spawner
+        .spawn(udp_tx_task(
+            stack,
+            source,
+            link_status_led,
+            data_send_led,
+            send_error_led,
+        ))
+        .expect("UDP task failed to spawn.");
+    spawner
+        .spawn(command_task(stack))
+        .expect("Command task failed to spawn.");
+    spawner
+        .spawn(trigger_task(trigger_pin))
+        .expect("Trigger task failed to spawn.");
+    spawner
+        .spawn(blackbox_task(stack))
+        .expect("Black-box task failed to spawn.");
+
+    let mut outlier_bank =
+        OutlierBank::new(&CHANNEL_OUTLIER_CONFIGS).expect("Invalid outlier configuration");
+    let mut filter_bank =
+        FilterBank::new(&CHANNEL_FILTERS, FILTER_DECIMATION).expect("Invalid filter configuration");
+    let mut alarm_engine =
+        AlarmEngine::<CHANNEL_COUNT>::with_rules(ALARM_RULES).expect("Invalid alarm rules");
+
+    let mut packet = SensorDataPacket::new();
+    let mut stats_bank = StatsBank::<CHANNEL_COUNT>::new();
+    let mut scheduler = SampleScheduler::new(Duration::from_micros(
+        CHANNEL_SCHEDULE.base_period_us as u64,
+    ));
+    let mut boosted = false;
+    let mut applied_offset_us = 0;
+    let mut last_status = Instant::now();
+    let mut streaming = TRIGGER_CONFIG.streams(false);
+    let mut marker_pending = false;
+
+    loop {
+        let trigger_active = TRIGGER_ACTIVE.load(Ordering::Relaxed);
+
+        // Switch sensor timing and schedule when the trigger boosts the rate
+        if TRIGGER_CONFIG.boosts(trigger_active) != boosted {
+            boosted = !boosted;
+            let plans = if boosted {
+                &BOOST_PLANS
+            } else {
+                &CHANNEL_PLANS
+            };
+            for (channel, plan) in acquisition.channels_mut().iter_mut().zip(plans) {
+                channel.frontend.set_timing(plan.timing);
+            }
+            if let Err((_sensor_num, _)) = acquisition.configure() {
+                info!("Failed to reconfigure sensor {}", _sensor_num);
+            }
+            let schedule = if boosted {
+                &BOOST_SCHEDULE
+            } else {
+                &CHANNEL_SCHEDULE
+            };
+            scheduler.set_period(Duration::from_micros(schedule.base_period_us as u64));
+            info!("Sample rate boost {}", if boosted { "on" } else { "off" });
+        }
+        let schedule = if boosted {
+            &BOOST_SCHEDULE
+        } else {
+            &CHANNEL_SCHEDULE
+        };
+
+        // Keep the sample grid on the shared timebase
+        let offset_us = CLOCK.lock(|clock| clock.borrow().offset_us());
+        if offset_us != applied_offset_us {
+            applied_offset_us = offset_us;
+            scheduler.set_offset(offset_us);
+        }
+
+        // Sample on a fixed grid regardless of how long the last iteration took
+        let tick = scheduler.next().await;
+        let due = schedule.due(tick);
+        // Edges seen before this tick mark the next samples stored, all of
+        // which are taken after the edge
+        marker_pending |= TRIGGER_EDGE.swap(false, Ordering::Relaxed);
+
+        // Read each due sensor with fault checking
+        let mut readings = match ACQUISITION_MODE {
+            AcquisitionMode::Sequential => acquisition.sample(&due),
+            AcquisitionMode::Simultaneous => {
+                acquisition
+                    .sample_simultaneous(&due, DRDY_TIMEOUT)
+                    .await
+                    .readings
+            }
+        };
 
+        // Average uncalibrated readings into a calibration point being captured
+        if let Some(_outcome) = calibration_session.add(&readings) {
+            info!("Calibration point: {:?}", _outcome);
+        }
+
+        // Correct readings with each channel's calibration
+        calibration.apply(&mut readings);
+
+        // Timestamp readings on the shared timebase
+        CLOCK.lock(|clock| {
+            let clock = clock.borrow();
+            for reading in readings.iter_mut().flatten() {
+                reading.timestamp_us = clock.to_synced(reading.timestamp_us);
+            }
+        });
+
+        // Log faults if present
+        for (channel, reading) in acquisition.channels().iter().zip(readings.iter()) {
+            if let Some(Reading {
+                faults: Some(faults),
+                ..
+            }) = reading
+            {
+                log_faults(channel.number, faults);
+            }
+        }
+
+        // Always print temperature readings (ADC counts)
+        info!(
+            "Temps [ADC]: {}",
+            readings.map(|reading| reading.map(|reading| reading.counts))
+        );
+
+        // Flag or replace implausible samples before they reach the filters
+        outlier_bank.process(&mut readings);
+        for (channel, reading) in acquisition.channels().iter().zip(readings.iter()) {
+            if let Some(reading) = reading {
+                log_quality(channel.number, reading.quality);
+            }
+        }
+
+        // Keep full-rate readings for the black box, before filtering and decimation
+        if BLACK_BOX.lock(|black_box| black_box.borrow_mut().record(&readings)) {
+            info!("Black box frozen");
+        }
+
+        // Raise alarm events straight away rather than with the next batch,
+        // checking every full-rate reading before filtering and decimation
+        for event in alarm_engine.evaluate(&readings) {
+            info!("Alarm: {:?}", event);
+            if BLACKBOX_FREEZE_ON_ALARM && event.transition == AlarmTransition::Raised {
+                BLACK_BOX.lock(|black_box| {
+                    black_box
+                        .borrow_mut()
+                        .trigger(FreezeReason::Alarm, event.timestamp_us)
+                });
+            }
+            let mut event = EventPacket::from(event);
+            event.packet_time = (synced_now_us() / 1000) as u32;
+            queue_event(event);
+        }
+
+        // Drive the interlock outputs from the alarm and fault state of the
+        // same full-rate readings
+        for event in interlocks.update(&alarm_engine, &readings) {
+            info!("Interlock: {:?}", event);
+            let mut event = interlock_event_packet(event, synced_now_us());
+            event.packet_time = (synced_now_us() / 1000) as u32;
+            queue_event(event);
+        }
+
+        // Filter in place; readings dropped by decimation are not stored
+        filter_bank.process(&mut readings);
+
+        // Run any command received since the last sample
+        if let Ok(command) = COMMAND_CHANNEL.try_receive() {
+            info!("Command: {:?}", command);
+            let result = match command {
+                Command::InterlockReset(index) => reset_interlocks(&mut interlocks, index),
+                Command::BlackBoxArm => BLACK_BOX
+                    .lock(|black_box| black_box.borrow_mut().arm())
+                    .map(|()| ReplyText::new())
+                    .map_err(|_| CommandError::Rejected("download in progress")),
+                Command::BlackBoxFreeze => {
+                    BLACK_BOX.lock(|black_box| black_box.borrow_mut().freeze(synced_now_us()));
+                    Ok(ReplyText::new())
+                }
+                Command::CalibrationCommit { .. } => {
+                    // Erasing and programming the sector blocks the whole
+                    // executor for tens of milliseconds. No task runs
+                    // meanwhile: samples are missed, queued packets and
+                    // black-box downloads wait, a master's sync pulse and
+                    // message go out late, and a follower timestamps a pulse
+                    // or sync message that arrived meanwhile late
+                    let result = run_calibration_command(
+                        command,
+                        &mut calibration_session,
+                        &mut calibration,
+                        &mut flash,
+                    );
+                    // Restart on the grid and forget history that assumes
+                    // samples one period apart
+                    scheduler.resume();
+                    outlier_bank.reset();
+                    alarm_engine.reset_rates();
+                    filter_bank.reset();
+                    result
+                }
+                Command::CalibrationStart { .. }
+                | Command::CalibrationPoint { .. }
+                | Command::CalibrationFit(_)
+                | Command::CalibrationStatus
+                | Command::CalibrationAbort => run_calibration_command(
+                    command,
+                    &mut calibration_session,
+                    &mut calibration,
+                    &mut flash,
+                ),
+                Command::FilterSelect { channel, preset } => {
+                    select_filter(&mut filter_bank, channel, preset)
+                }
+            };
+            let _ = REPLY_CHANNEL.try_send(result);
+        }
+
+        // Store readings in each channel's batch, unless the trigger gates streaming
+        let was_streaming = streaming;
+        streaming = TRIGGER_CONFIG.streams(trigger_active);
+        if streaming {
+            if marker_pending && readings.iter().any(Option::is_some) {
+                marker_pending = false;
+                for reading in readings.iter_mut().flatten() {
+                    reading.quality.insert(QualityFlags::TRIGGER);
+                }
+            }
+            packet.store(&readings);
+            if STREAM_MODE.sends_summary() {
+                stats_bank.add(&readings);
+            }
+        } else {
+            // Nothing is stored to mark; the trigger event still records the edge
+            marker_pending = false;
+        }
+        // Send partial batches and windows as soon as streaming stops
+        let gate_closed = was_streaming && !streaming;
+
+        let now_us = synced_now_us();
+
+        // Emit summary statistics when the window closes
+        let summary_due = match SUMMARY_WINDOW {
+            SummaryWindow::Batch => packet.is_full() || (gate_closed && !packet.is_empty()),
+            SummaryWindow::Millis(window_ms) => {
+                stats_bank.window_start_us().is_some_and(|start_us| {
+                    gate_closed || now_us.saturating_sub(start_us) >= window_ms as u64 * 1000
+                })
+            }
+        };
+        if STREAM_MODE.sends_summary() && summary_due {
+            let window_start_us = stats_bank.window_start_us().unwrap_or(now_us);
+            let mut summary = SummaryPacket::new(
+                window_start_us,
+                now_us,
+                stats_bank.take().map(ChannelSummary::from),
+            );
+            summary.packet_time = (now_us / 1000) as u32;
+            queue_packet(OutgoingPacket::Summary(summary));
+        }
+
+        // Report sync and timing health
+        if last_status.elapsed() >= Duration::from_millis(STATUS_INTERVAL_MS) {
+            last_status = Instant::now();
+            let local_us = last_status.as_micros();
+            let mut status = CLOCK.lock(|clock| {
+                status_packet(
+                    &clock.borrow(),
+                    &scheduler.stats(),
+                    &link_stats(),
+                    calibration.channels(),
+                    local_us,
+                )
+            });
+            status.packet_time = (now_us / 1000) as u32;
+            queue_packet(OutgoingPacket::Status(status));
+        }
+
+        // When a batch is full, hand the packet to the UDP task
+        if packet.is_full() || (gate_closed && !packet.is_empty()) {
+            if STREAM_MODE.sends_data() {
+                // packet.packet_tag = 9;
+                packet.packet_time = (now_us / 1000) as u32;
+                queue_packet(OutgoingPacket::Data(packet));
+            }
+
+            let _stats = scheduler.stats();
+            info!(
+                "Scheduler: {} overruns, jitter {} us (max {} us)",
+                _stats.overruns, _stats.last_jitter_us, _stats.max_jitter_us
+            );
+
+            packet = SensorDataPacket::new();
+        }
+    }
+}
+
+/// Send queued packets over UDP, blinking the status LEDs
+#[embassy_executor::task]
+async fn udp_tx_task(
+    stack: Stack<'static>,
+    source: PacketSource,
+    mut link_status_led: Output<'static>,
+    mut data_send_led: Output<'static>,
+    mut send_error_led: Output<'static>,
+) -> ! {
     // UDP socket setup - increased buffer sizes
     let mut rx_meta = [PacketMetadata::EMPTY; 16];
     let mut rx_buffer = [0; 2048];
@@ -242,96 +896,323 @@ async fn main(spawner: Spawner) -> ! {
     let remote_endpoint = (Ipv4Address::new(192, 168, 88, 251), 1684);
     info!("Will send UDP packets to {:?}", remote_endpoint);
 
-    let mut packet = SensorDataPacket::new();
-    let mut batch_index = 0usize;
+    // Sequence number of the next packet, counting every packet taken off the queues
     let mut packet_counter = 0u32;
+    let mut payload = [0u8; MAX_PAYLOAD_LEN];
+    let mut frame = [0u8; MAX_PACKET_LEN];
 
     loop {
-        // Read each sensor with fault checking
-        let (tc1, faults1) =
-            max31856::read_thermocouple_with_fault_check(&mut spi_dev1, &mut nfault1, &mut ndrdy1)
-                .await;
-        let (tc2, faults2) =
-            max31856::read_thermocouple_with_fault_check(&mut spi_dev2, &mut nfault2, &mut ndrdy2)
-                .await;
-        let (tc3, faults3) =
-            max31856::read_thermocouple_with_fault_check(&mut spi_dev3, &mut nfault3, &mut ndrdy3)
-                .await;
-        let (tc4, faults4) =
-            max31856::read_thermocouple_with_fault_check(&mut spi_dev4, &mut nfault4, &mut ndrdy4)
-                .await;
+        // Events win over batches when both are waiting
+        let packet = match select(EVENT_CHANNEL.receive(), PACKET_CHANNEL.receive()).await {
+            Either::First(event) => OutgoingPacket::Event(event),
+            Either::Second(packet) => packet,
+        };
 
-        // Log faults if present
-        if let Some(ref faults) = faults1 {
-            log_faults(1, faults);
+        while !stack.is_link_up() {
+            link_status_led.set_low();
+            Timer::after_millis(100).await;
+            link_status_led.set_high();
+            Timer::after_millis(100).await;
         }
-        if let Some(ref faults) = faults2 {
-            log_faults(2, faults);
+
+        let payload_length = packet
+            .encode_into(&mut payload)
+            .expect("Payload buffer sized for the largest packet");
+        let length = frame_packet(
+            PACKET_FORMAT,
+            &source,
+            packet_counter,
+            &payload[..payload_length],
+            &mut frame,
+        );
+
+        // Send UDP packet with timeout to prevent hanging
+        data_send_led.set_high();
+
+        match select(
+            udp_socket.send_to(&frame[..length], remote_endpoint),
+            Timer::after(Duration::from_secs(1)),
+        )
+        .await
+        {
+            Either::First(Ok(_)) => {
+                info!("Sent packet #{} ({} bytes)", packet_counter, length);
+            }
+            Either::First(Err(_e)) => {
+                SEND_ERRORS.fetch_add(1, Ordering::Relaxed);
+                info!("UDP send error: {:?}", _e);
+                send_error_led.set_high();
+                Timer::after_millis(100).await;
+                send_error_led.set_low();
+            }
+            Either::Second(_) => {
+                SEND_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+                info!("UDP send timeout - packet #{}", packet_counter);
+                send_error_led.set_high();
+                Timer::after_millis(100).await;
+                send_error_led.set_low();
+            }
         }
-        if let Some(ref faults) = faults3 {
-            log_faults(3, faults);
+        data_send_led.set_low();
+
+        packet_counter = packet_counter.wrapping_add(1);
+    }
+}
+
+/// Receive commands over UDP and reply with their results
+#[embassy_executor::task]
+async fn command_task(stack: Stack<'static>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; 512];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0; 512];
+
+    let mut udp_socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    udp_socket.bind(COMMAND_PORT).unwrap();
+    info!("Listening for commands on port {}", COMMAND_PORT);
+
+    let mut datagram = [0; 128];
+    let mut reply = [0; 128];
+    loop {
+        let Ok((length, sender)) = udp_socket.recv_from(&mut datagram).await else {
+            continue;
+        };
+
+        let result = match Command::parse(&datagram[..length]) {
+            Ok(command) => {
+                // Discard a reply left over from a command that timed out
+                let _ = REPLY_CHANNEL.try_receive();
+                COMMAND_CHANNEL.send(command).await;
+                match select(REPLY_CHANNEL.receive(), Timer::after(COMMAND_TIMEOUT)).await {
+                    Either::First(result) => result,
+                    Either::Second(_) => Err(CommandError::Timeout),
+                }
+            }
+            Err(error) => Err(error),
+        };
+
+        let length = format_reply(&result, &mut reply);
+        if udp_socket.send_to(&reply[..length], sender).await.is_err() {
+            info!("Command reply failed");
         }
-        if let Some(ref faults) = faults4 {
-            log_faults(4, faults);
+    }
+}
+
+/// Timestamp trigger input edges as the task wakes and send them as marker events
+#[embassy_executor::task]
+async fn trigger_task(mut input: ExtiInput<'static>) -> ! {
+    let mut active = false;
+    loop {
+        let level_active = TRIGGER_CONFIG.is_active(input.is_high());
+        if level_active == active {
+            input.wait_for_any_edge().await;
+            continue;
         }
 
-        // Always print temperature readings (ADC counts)
-        info!("Temps [ADC]: {} {} {} {}", tc1, tc2, tc3, tc4);
+        let timestamp_us = synced_now_us();
+        active = level_active;
+        TRIGGER_ACTIVE.store(active, Ordering::Relaxed);
+        TRIGGER_EDGE.store(true, Ordering::Relaxed);
+        info!(
+            "Trigger {} at {} us",
+            if active { "asserted" } else { "released" },
+            timestamp_us
+        );
 
-        while !stack.is_link_up() {
-            link_status_led.set_low();
-            Timer::after_millis(100).await;
-            link_status_led.set_high();
-            Timer::after_millis(100).await;
+        if BLACKBOX_FREEZE_ON_TRIGGER && active {
+            BLACK_BOX.lock(|black_box| {
+                black_box
+                    .borrow_mut()
+                    .trigger(FreezeReason::Trigger, timestamp_us)
+            });
         }
 
-        // Store readings in batch
-        packet.tc1_temps[batch_index] = tc1;
-        packet.tc2_temps[batch_index] = tc2;
-        packet.tc3_temps[batch_index] = tc3;
-        packet.tc4_temps[batch_index] = tc4;
+        let mut event = EventPacket::trigger(active, timestamp_us);
+        event.packet_time = (synced_now_us() / 1000) as u32;
+        queue_event(event);
 
-        batch_index += 1;
+        // Let contact bounce settle before looking at the level again
+        Timer::after_micros(TRIGGER_CONFIG.debounce_us as u64).await;
+    }
+}
 
-        // When batch is full, send UDP packet
-        if batch_index >= BATCH_SIZE {
-            // packet.packet_tag = 9;
-            packet.packet_time = embassy_time::Instant::now().as_millis() as u32;
+/// Drive the sync pulse and broadcast sync messages on every whole sync period
+#[embassy_executor::task]
+async fn sync_master_task(stack: Stack<'static>, mut pulse: Option<Output<'static>>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0; 64];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0; 256];
 
-            // Send UDP packet with timeout to prevent hanging
-            data_send_led.set_high();
+    let mut udp_socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    udp_socket.bind(0).unwrap();
 
-            match select(
-                udp_socket.send_to(packet.as_bytes(), remote_endpoint),
-                Timer::after(Duration::from_secs(1)),
-            )
-            .await
+    let mut sequence = 0u32;
+    loop {
+        // The master's clock is the timebase, so local time is shared time
+        let now_us = Instant::now().as_micros();
+        let next_us = (now_us / SYNC_PERIOD_US + 1) * SYNC_PERIOD_US;
+        Timer::at(Instant::from_micros(next_us)).await;
+
+        if let Some(pulse) = pulse.as_mut() {
+            pulse.set_high();
+        }
+        if SYNC_CONFIG.network {
+            let message = SyncMessage {
+                sequence,
+                master_time_us: Instant::now().as_micros(),
+            };
+            if udp_socket
+                .send_to(&message.to_bytes(), (SYNC_BROADCAST, SYNC_PORT))
+                .await
+                .is_err()
             {
-                Either::First(Ok(_)) => {
-                    info!(
-                        "Sent packet #{} with {} readings",
-                        packet_counter, BATCH_SIZE
-                    );
-                }
-                Either::First(Err(_e)) => {
-                    info!("UDP send error: {:?}", _e);
-                    send_error_led.set_high();
-                    Timer::after_millis(100).await;
-                    send_error_led.set_low();
-                }
-                Either::Second(_) => {
-                    info!("UDP send timeout - packet #{}", packet_counter);
-                    send_error_led.set_high();
-                    Timer::after_millis(100).await;
-                    send_error_led.set_low();
-                }
+                info!("Sync message send failed");
             }
-            data_send_led.set_low();
+            sequence = sequence.wrapping_add(1);
+        }
+        if let Some(pulse) = pulse.as_mut() {
+            Timer::at(Instant::from_micros(next_us) + SYNC_PULSE_WIDTH).await;
+            pulse.set_low();
+        }
+    }
+}
+
+/// Correct the clock phase on every rising edge of the sync pulse
+#[embassy_executor::task]
+async fn sync_pulse_task(mut input: ExtiInput<'static>) -> ! {
+    loop {
+        input.wait_for_rising_edge().await;
+        let local_us = Instant::now().as_micros();
+        let (_error_us, _offset_us) = CLOCK.lock(|clock| {
+            let mut clock = clock.borrow_mut();
+            clock.on_pulse(local_us);
+            (clock.last_error_us(), clock.offset_us())
+        });
+        info!(
+            "Sync pulse: error {} us, offset {} us",
+            _error_us, _offset_us
+        );
+    }
+}
+
+/// Correct the clock from the master's UDP sync messages
+#[embassy_executor::task]
+async fn sync_listen_task(stack: Stack<'static>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; 256];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; 64];
 
-            packet_counter += 1;
-            batch_index = 0;
+    let mut udp_socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    udp_socket.bind(SYNC_PORT).unwrap();
+
+    let mut datagram = [0; 64];
+    loop {
+        let Ok((length, _)) = udp_socket.recv_from(&mut datagram).await else {
+            continue;
+        };
+        let local_us = Instant::now().as_micros();
+        let Some(message) = SyncMessage::from_bytes(&datagram[..length]) else {
+            continue;
+        };
+        let _error_us = CLOCK.lock(|clock| {
+            let mut clock = clock.borrow_mut();
+            clock.on_network(message.master_time_us, local_us);
+            clock.last_error_us()
+        });
+        info!("Sync message #{}: error {} us", message.sequence, _error_us);
+    }
+}
+
+/// Serve the black-box contents to each TCP client, then close the connection
+#[embassy_executor::task]
+async fn blackbox_task(stack: Stack<'static>) -> ! {
+    let mut rx_buffer = [0; 64];
+    let mut tx_buffer = [0; 2048];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if socket.accept(BLACKBOX_PORT).await.is_err() {
+            continue;
         }
+        info!("Black-box download started");
 
-        Timer::after_millis(100).await;
+        // Hold the contents still while they are sent, without touching the
+        // recorder's state, so events during the download are not lost
+        let header = BLACK_BOX.lock(|black_box| {
+            black_box
+                .borrow_mut()
+                .start_download::<CHANNEL_COUNT>(synced_now_us())
+        });
+
+        let mut result = write_all(&mut socket, &header).await;
+        let mut index = 0;
+        let mut chunk = [0; BLACKBOX_RECORD_LEN * 32];
+        while result.is_ok() {
+            let length = BLACK_BOX.lock(|black_box| {
+                let black_box = black_box.borrow();
+                let mut length = 0;
+                while length < chunk.len() {
+                    let Some(record) = black_box.get(index) else {
+                        break;
+                    };
+                    chunk[length..length + BLACKBOX_RECORD_LEN].copy_from_slice(&record.to_bytes());
+                    length += BLACKBOX_RECORD_LEN;
+                    index += 1;
+                }
+                length
+            });
+            if length == 0 {
+                break;
+            }
+            result = write_all(&mut socket, &chunk[..length]).await;
+        }
+        if result.is_ok() {
+            result = socket.flush().await;
+        }
+        match result {
+            Ok(()) => {
+                info!("Black-box download sent {} records", index);
+            }
+            Err(_e) => {
+                info!("Black-box download failed: {:?}", _e);
+            }
+        }
+        socket.close();
+        let _ = socket.flush().await;
+
+        BLACK_BOX.lock(|black_box| black_box.borrow_mut().end_download());
+    }
+}
+
+/// Write all of `bytes` to a TCP socket
+async fn write_all(socket: &mut TcpSocket<'_>, mut bytes: &[u8]) -> Result<(), tcp::Error> {
+    while !bytes.is_empty() {
+        let written = socket.write(bytes).await?;
+        if written == 0 {
+            return Err(tcp::Error::ConnectionReset);
+        }
+        bytes = &bytes[written..];
     }
+    Ok(())
 }
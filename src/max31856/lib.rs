@@ -1,8 +1,11 @@
 use crate::max31856::registers::*;
-use embedded_hal::digital::InputPin;
 use embedded_hal::spi::SpiDevice;
 
-#[derive(Debug, Clone, Copy)]
+/// Reading reported by the linearized TC register when the input is open
+/// but the fault status has not latched yet
+pub const OPEN_CIRCUIT_COUNTS: i32 = 175623;
+
+#[derive(Debug, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct FaultStatus {
     pub cj_range: bool, // Cold-Junction Out-of-Range
@@ -39,6 +42,26 @@ impl FaultStatus {
             || self.ovuv
             || self.open
     }
+
+    /// Pack back into the MAX31856 status register layout
+    pub fn to_register(&self) -> u8 {
+        let mut reg = 0;
+        for (set, bit) in [
+            (self.cj_range, SR_CJ_RANGE),
+            (self.tc_range, SR_TC_RANGE),
+            (self.cj_high, SR_CJ_HIGH),
+            (self.cj_low, SR_CJ_LOW),
+            (self.tc_high, SR_TC_HIGH),
+            (self.tc_low, SR_TC_LOW),
+            (self.ovuv, SR_OVUV),
+            (self.open, SR_OPEN),
+        ] {
+            if set {
+                reg |= bit;
+            }
+        }
+        reg
+    }
 }
 
 pub fn read_fault_status<SPI>(spi: &mut SPI) -> Result<FaultStatus, SPI::Error>
@@ -73,6 +96,19 @@ where
     Ok(())
 }
 
+/// Start a one-shot conversion (CR0 bit 6)
+/// Only has an effect in normally-off mode; the bit self-clears when done
+pub fn trigger_one_shot<SPI>(spi: &mut SPI) -> Result<(), SPI::Error>
+where
+    SPI: SpiDevice,
+{
+    let mut buffer = [0u8; 2];
+    buffer[0] = CR0_READ;
+    spi.transfer_in_place(&mut buffer)?;
+
+    spi.write(&[CR0_WRITE, buffer[1] | CR0_ONESHOT])
+}
+
 /// Read multiple registers for debugging
 pub fn read_all_config_registers<SPI>(spi: &mut SPI) -> Result<[u8; 16], SPI::Error>
 where
@@ -152,56 +188,68 @@ where
     SPI: SpiDevice,
 {
     // Convert offset to 8-bit value (resolution 0.0625°C, but stored as 4-bit fractional)
+    // Out-of-range offsets saturate at -8°C / +7.9375°C
     let offset_raw = (offset_celsius * 16.0) as i8;
     spi.write(&[CJTO_WRITE, offset_raw as u8])
 }
 
-pub async fn read_thermocouple_with_fault_check<SPI, FAULT, DRDY>(
-    spi: &mut SPI,
-    _fault_pin: &mut FAULT,
-    _drdy_pin: &mut DRDY, // Unused in INTERRUPT mode
-) -> (i32, Option<FaultStatus>)
+/// Read the linearized thermocouple temperature (0x0C-0x0E)
+/// Returns the 19-bit value in counts of 0.0078125°C
+pub fn read_temperature_counts<SPI>(spi: &mut SPI) -> Result<i32, SPI::Error>
 where
     SPI: SpiDevice,
-    FAULT: InputPin,
-    DRDY: InputPin,
 {
-    // First check fault status before reading temperature
-    let mut fault_buffer = [0u8; 2];
-    fault_buffer[0] = SR_READ;
-    let mut fault_status = if spi.transfer_in_place(&mut fault_buffer).is_ok() {
-        let status = FaultStatus::from_register(fault_buffer[1]);
-
-        if status.has_fault() {
-            // Clear the faults
-            let _ = clear_faults(spi);
-            Some(status)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-
-    // If there's a fault, return 0 for temperature
-    if fault_status.is_some() {
-        return (0, fault_status);
-    }
-
     // Read 3 bytes of temperature data starting from LTCBH
     let mut buffer = [0u8; 4];
     buffer[0] = LTCBH_READ;
+    spi.transfer_in_place(&mut buffer)?;
+
+    // The 19-bit two's complement value is left-aligned in the three bytes.
+    // Place it at the top of an i32, then shift arithmetically so negative
+    // temperatures keep their sign
+    let raw_val =
+        ((buffer[1] as i32) << 24) | ((buffer[2] as i32) << 16) | ((buffer[3] as i32) << 8);
+    Ok(raw_val >> 13)
+}
+
+/// Read the cold-junction temperature (0x0A-0x0B)
+/// Returns the signed 14-bit value in counts of 0.015625°C
+pub fn read_cold_junction_counts<SPI>(spi: &mut SPI) -> Result<i32, SPI::Error>
+where
+    SPI: SpiDevice,
+{
+    let mut buffer = [0u8; 3];
+    buffer[0] = CJTH_READ;
+    spi.transfer_in_place(&mut buffer)?;
+
+    let raw = i16::from_be_bytes([buffer[1], buffer[2]]);
+    Ok((raw >> 2) as i32)
+}
+
+/// Read a thermocouple, clearing and reporting any latched fault
+/// A faulted read returns 0 for the temperature; a failed transfer returns the error
+pub fn read_thermocouple_checked<SPI>(
+    spi: &mut SPI,
+) -> Result<(i32, Option<FaultStatus>), SPI::Error>
+where
+    SPI: SpiDevice,
+{
+    // First check fault status before reading temperature
+    let status = read_fault_status(spi)?;
+    // Reading the temperature releases nDRDY, so it is read even when
+    // faulted; otherwise the next one-shot conversion would look ready at once
+    let temperature = read_temperature_counts(spi);
+    if status.has_fault() {
+        // Clear the faults and return 0 for temperature
+        let _ = clear_faults(spi);
+        return Ok((0, Some(status)));
+    }
+    let mut fault_status = None;
 
-    let temp_counts = if spi.transfer_in_place(&mut buffer).is_ok() {
-        // The data format is: [raw_val[0] << 16] | [raw_val[1] << 8] | [raw_val[2]]
-        // Then shift right by 5 to get the 19-bit value
-        let mut raw_val_signed =
-            ((buffer[1] as i32) << 16) | ((buffer[2] as i32) << 8) | (buffer[3] as i32);
-        raw_val_signed >>= 5;
+    let temp_counts = match temperature {
         // Sometimes ndrdy is faster than nfault, leading to an OC error.
         // During this event, the max reading is given, so we return 0 and generate and error.
-        if raw_val_signed == 175623 {
-            raw_val_signed = 0;
+        Ok(OPEN_CIRCUIT_COUNTS) => {
             fault_status = Some(FaultStatus {
                 cj_range: false,
                 tc_range: false,
@@ -212,11 +260,11 @@ where
                 ovuv: false,
                 open: true,
             });
+            0
         }
-        raw_val_signed
-    } else {
-        0 // Return 0 if SPI read fails
+        Ok(counts) => counts,
+        Err(e) => return Err(e),
     };
 
-    (temp_counts, fault_status)
+    Ok((temp_counts, fault_status))
 }
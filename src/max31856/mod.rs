@@ -1,6 +1,7 @@
 #![deny(unsafe_code)]
 
 pub mod registers;
+pub mod timing;
 
 mod lib;
 pub use lib::*;
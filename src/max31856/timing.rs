@@ -0,0 +1,247 @@
+//! MAX31856 conversion timing
+//!
+//! Conversion times are the datasheet maximums, so a plan built from them
+//! never samples faster than the chip produces fresh data.
+
+use crate::max31856::registers::*;
+
+/// Mains noise rejection filter (CR0 bit 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NoiseFilter {
+    Hz60,
+    Hz50,
+}
+
+/// Conversion mode (CR0 bit 7)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConversionMode {
+    /// Normally off, one conversion per one-shot trigger
+    OneShot,
+    /// Automatic back-to-back conversions
+    Continuous,
+}
+
+/// Open-circuit fault detection (CR0 bits 5:4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OcDetection {
+    Disabled,
+    /// RS < 5k
+    RsLt5k,
+    /// 40k > RS > 5k, time constant < 2ms
+    TcLt2ms,
+    /// 40k > RS > 5k, time constant > 2ms
+    TcGt2ms,
+}
+
+/// Samples averaged per conversion (CR1 bits 6:4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Averaging {
+    X1,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl Averaging {
+    /// All settings, from fastest to slowest
+    pub const ALL: [Averaging; 5] = [
+        Averaging::X1,
+        Averaging::X2,
+        Averaging::X4,
+        Averaging::X8,
+        Averaging::X16,
+    ];
+
+    pub const fn samples(self) -> u32 {
+        match self {
+            Averaging::X1 => 1,
+            Averaging::X2 => 2,
+            Averaging::X4 => 4,
+            Averaging::X8 => 8,
+            Averaging::X16 => 16,
+        }
+    }
+
+    pub const fn cr1_bits(self) -> u8 {
+        match self {
+            Averaging::X1 => CR1_AVG_1_SAMPLE,
+            Averaging::X2 => CR1_AVG_2_SAMPLES,
+            Averaging::X4 => CR1_AVG_4_SAMPLES,
+            Averaging::X8 => CR1_AVG_8_SAMPLES,
+            Averaging::X16 => CR1_AVG_16_SAMPLES,
+        }
+    }
+}
+
+/// Every register setting that affects conversion time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensorTiming {
+    pub filter: NoiseFilter,
+    pub averaging: Averaging,
+    pub mode: ConversionMode,
+    pub oc_detection: OcDetection,
+}
+
+impl SensorTiming {
+    /// CR0 bits for the filter, open-circuit detection and conversion mode
+    pub const fn cr0_bits(&self) -> u8 {
+        let filter = match self.filter {
+            NoiseFilter::Hz60 => CR0_FILTER_60HZ,
+            NoiseFilter::Hz50 => CR0_FILTER_50HZ,
+        };
+        let oc = match self.oc_detection {
+            OcDetection::Disabled => CR0_OC_DISABLED,
+            OcDetection::RsLt5k => CR0_OC_ENABLED_RS_LT_5K,
+            OcDetection::TcLt2ms => CR0_OC_ENABLED_TC_LESS_2MS,
+            OcDetection::TcGt2ms => CR0_OC_ENABLED_TC_MORE_2MS,
+        };
+        let mode = match self.mode {
+            ConversionMode::OneShot => CR0_CONV_NORMALLY_OFF,
+            ConversionMode::Continuous => CR0_CONV_CONTINUOUS,
+        };
+        filter | oc | mode
+    }
+
+    /// CR1 averaging bits
+    pub const fn cr1_bits(&self) -> u8 {
+        self.averaging.cr1_bits()
+    }
+
+    /// Maximum time for one conversion result, in microseconds
+    pub const fn conversion_time_us(&self) -> u32 {
+        conversion_time_us(self.filter, self.averaging, self.mode, self.oc_detection)
+    }
+}
+
+/// Maximum time for one conversion result, in microseconds
+///
+/// tCONV = first sample + (AVGSEL - 1) * additional sample + OC detection time
+pub const fn conversion_time_us(
+    filter: NoiseFilter,
+    averaging: Averaging,
+    mode: ConversionMode,
+    oc_detection: OcDetection,
+) -> u32 {
+    let first_sample_us = match (mode, filter) {
+        (ConversionMode::Continuous, NoiseFilter::Hz60) => 90_000,
+        (ConversionMode::Continuous, NoiseFilter::Hz50) => 100_000,
+        (ConversionMode::OneShot, NoiseFilter::Hz60) => 155_000,
+        (ConversionMode::OneShot, NoiseFilter::Hz50) => 185_000,
+    };
+    let additional_sample_us = match filter {
+        NoiseFilter::Hz60 => 33_333,
+        NoiseFilter::Hz50 => 40_000,
+    };
+    // Open-circuit test time is added to every conversion
+    let oc_detection_us = match oc_detection {
+        OcDetection::Disabled => 0,
+        OcDetection::RsLt5k => 13_000,
+        OcDetection::TcLt2ms => 33_000,
+        OcDetection::TcGt2ms => 40_000,
+    };
+
+    first_sample_us + (averaging.samples() - 1) * additional_sample_us + oc_detection_us
+}
+
+/// Timing chosen for a requested per-channel sample rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SamplePlan {
+    pub timing: SensorTiming,
+    /// Time per conversion with the chosen averaging
+    pub conversion_time_us: u32,
+    /// Time between samples of the same channel
+    pub sample_period_us: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PlanError {
+    /// A rate of 0 was requested
+    ZeroRate,
+    /// Even without averaging the chip cannot convert this fast
+    Infeasible {
+        /// Highest achievable rate with these settings, in millihertz
+        max_rate_mhz: u32,
+    },
+}
+
+/// Pick the heaviest averaging that still meets a per-channel sample rate
+///
+/// `rate_mhz` is in millihertz so sub-1Hz rates can be requested.
+pub const fn plan_sample_rate(
+    rate_mhz: u32,
+    filter: NoiseFilter,
+    mode: ConversionMode,
+    oc_detection: OcDetection,
+) -> Result<SamplePlan, PlanError> {
+    if rate_mhz == 0 {
+        return Err(PlanError::ZeroRate);
+    }
+    let sample_period_us = (1_000_000_000u64 / rate_mhz as u64) as u32;
+
+    let mut best = None;
+    let mut i = 0;
+    while i < Averaging::ALL.len() {
+        let timing = SensorTiming {
+            filter,
+            averaging: Averaging::ALL[i],
+            mode,
+            oc_detection,
+        };
+        if timing.conversion_time_us() <= sample_period_us {
+            best = Some(timing);
+        }
+        i += 1;
+    }
+
+    match best {
+        Some(timing) => Ok(SamplePlan {
+            timing,
+            conversion_time_us: timing.conversion_time_us(),
+            sample_period_us,
+        }),
+        None => {
+            let fastest = conversion_time_us(filter, Averaging::X1, mode, oc_detection);
+            Err(PlanError::Infeasible {
+                max_rate_mhz: 1_000_000_000 / fastest,
+            })
+        }
+    }
+}
+
+/// Plan every channel's rate with shared filter, mode and OC settings
+/// On failure, returns the 0-based index of the first infeasible channel
+pub const fn plan_channel_rates<const N: usize>(
+    rates_mhz: &[u32; N],
+    filter: NoiseFilter,
+    mode: ConversionMode,
+    oc_detection: OcDetection,
+) -> Result<[SamplePlan; N], (usize, PlanError)> {
+    let placeholder = SamplePlan {
+        timing: SensorTiming {
+            filter,
+            averaging: Averaging::X1,
+            mode,
+            oc_detection,
+        },
+        conversion_time_us: 0,
+        sample_period_us: 0,
+    };
+    let mut plans = [placeholder; N];
+    let mut i = 0;
+    while i < N {
+        plans[i] = match plan_sample_rate(rates_mhz[i], filter, mode, oc_detection) {
+            Ok(plan) => plan,
+            Err(e) => return Err((i, e)),
+        };
+        i += 1;
+    }
+    Ok(plans)
+}
@@ -0,0 +1,59 @@
+//! Outlier rejection applied to sample sets
+//!
+//! The per-channel median and slew checks are in the `thermosoft-filter`
+//! crate (`filter/`), which is tested on the host; this module applies them
+//! to the board's readings.
+
+pub use thermosoft_filter::outlier::*;
+
+use crate::acquisition::Reading;
+
+/// Outlier filters for every channel
+pub struct OutlierBank<const N: usize> {
+    filters: [OutlierFilter; N],
+}
+
+impl<const N: usize> OutlierBank<N> {
+    pub fn new(configs: &[OutlierConfig; N]) -> Result<Self, OutlierError> {
+        for config in configs {
+            config.validate()?;
+        }
+        Ok(Self {
+            filters: configs.map(OutlierFilter::with_valid_config),
+        })
+    }
+
+    /// Change the settings of one channel (0-based index), clearing its history
+    pub fn set_channel_config(
+        &mut self,
+        index: usize,
+        config: OutlierConfig,
+    ) -> Result<(), OutlierError> {
+        self.filters[index] = OutlierFilter::new(config)?;
+        Ok(())
+    }
+
+    /// Clear every channel's history, e.g. after a gap in sampling
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    /// Check a sample set in place, recording decisions in each reading's flags
+    /// Faulted readings are skipped and clear that channel's history
+    pub fn process(&mut self, readings: &mut [Option<Reading>; N]) {
+        for (filter, reading) in self.filters.iter_mut().zip(readings.iter_mut()) {
+            let Some(reading) = reading else {
+                continue;
+            };
+            if !reading.is_valid() {
+                filter.reset();
+                continue;
+            }
+            let (counts, flags) = filter.process(reading.counts, reading.timestamp_us);
+            reading.counts = counts;
+            reading.quality.insert(flags);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Per-sample data quality flags
+
+pub use thermosoft_protocol::quality::QualityFlags;
+
+use crate::max31856::FaultStatus;
+
+/// Flags for a fresh reading with the given converter fault state
+pub fn from_faults(faults: Option<&FaultStatus>) -> QualityFlags {
+    let Some(faults) = faults else {
+        return QualityFlags::VALID;
+    };
+    let mut flags = QualityFlags::NONE;
+    for (set, flag) in [
+        (faults.open, QualityFlags::OPEN),
+        (faults.ovuv, QualityFlags::OVUV),
+        (
+            faults.tc_range || faults.tc_high || faults.tc_low,
+            QualityFlags::TC_RANGE,
+        ),
+        (
+            faults.cj_range || faults.cj_high || faults.cj_low,
+            QualityFlags::CJ_RANGE,
+        ),
+    ] {
+        if set {
+            flags.insert(flag);
+        }
+    }
+    flags
+}
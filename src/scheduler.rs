@@ -0,0 +1,176 @@
+//! Drift-free fixed-rate sample scheduling
+//!
+//! Sample instants are kept on an absolute grid (start + k * period), so time
+//! spent reading, filtering or logging never accumulates into the period.
+//! Deadlines that are missed entirely are skipped and counted as overruns
+//! rather than fired back-to-back. With a clock offset set, the grid is
+//! placed on whole periods of the shared multi-board timebase instead.
+
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::max31856::timing::SamplePlan;
+
+/// Timing statistics since the last reset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SchedulerStats {
+    /// Sample instants delivered
+    pub ticks: u32,
+    /// Sample instants skipped because the previous sample ran too long
+    pub overruns: u32,
+    /// Lateness of the most recent wake-up, in microseconds
+    pub last_jitter_us: u32,
+    /// Largest lateness seen, in microseconds
+    pub max_jitter_us: u32,
+}
+
+/// Wakes the sampling loop once per period on a fixed grid
+pub struct SampleScheduler {
+    period: Duration,
+    next: Instant,
+    index: u64,
+    offset_us: i64,
+    stats: SchedulerStats,
+}
+
+impl SampleScheduler {
+    /// Start a grid whose first instant is one period from now
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            next: Instant::now() + period,
+            index: 0,
+            offset_us: 0,
+            stats: SchedulerStats::default(),
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Switch to a new period, restarting the grid on the next whole period
+    pub fn set_period(&mut self, period: Duration) {
+        self.period = period;
+        self.realign();
+    }
+
+    /// Follow a clock offset (shared time minus local time), moving the grid
+    /// onto whole periods of shared time
+    pub fn set_offset(&mut self, offset_us: i64) {
+        self.offset_us = offset_us;
+        self.realign();
+    }
+
+    /// Continue after the caller blocked the executor, such as for a flash
+    /// erase. The instants missed meanwhile are skipped without counting as
+    /// overruns, and the grid restarts on the next whole period
+    pub fn resume(&mut self) {
+        self.realign();
+    }
+
+    /// Put the next instant on the next whole period of shared time
+    /// The grid index counts periods of shared time, so boards sharing a
+    /// timebase also agree on which ticks slower channels are due
+    fn realign(&mut self) {
+        let period = self.period.as_micros() as i64;
+        let synced_now = Instant::now().as_micros() as i64 + self.offset_us;
+        let synced_next = (synced_now.div_euclid(period) + 1) * period;
+        self.next = Instant::from_micros((synced_next - self.offset_us).max(0) as u64);
+        self.index = (synced_next / period).max(0) as u64;
+    }
+
+    /// Wait for the next sample instant and return its grid index
+    /// Skipped instants still advance the index, keeping channel phases fixed
+    pub async fn next(&mut self) -> u64 {
+        Timer::at(self.next).await;
+
+        let now = Instant::now();
+        let late = now.saturating_duration_since(self.next);
+        let jitter_us = late.as_micros().min(u32::MAX as u64) as u32;
+        self.stats.last_jitter_us = jitter_us;
+        self.stats.max_jitter_us = self.stats.max_jitter_us.max(jitter_us);
+
+        // Skip whole periods that have already passed, staying on the grid
+        let missed = late.as_ticks() / self.period.as_ticks();
+        if missed > 0 {
+            self.stats.overruns = self.stats.overruns.saturating_add(missed as u32);
+            self.next += Duration::from_ticks(self.period.as_ticks() * missed);
+            self.index += missed;
+        }
+
+        let index = self.index;
+        self.next += self.period;
+        self.index += 1;
+        self.stats.ticks = self.stats.ticks.wrapping_add(1);
+        index
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = SchedulerStats::default();
+    }
+}
+
+/// Which channels are due on each tick of the fastest channel's grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelSchedule<const N: usize> {
+    /// Period of the fastest channel, the scheduler grid
+    pub base_period_us: u32,
+    /// Sample period of each channel
+    pub periods_us: [u32; N],
+    /// Grid ticks between samples of each channel
+    pub dividers: [u32; N],
+    /// Longest conversion time of any channel
+    pub max_conversion_time_us: u32,
+}
+
+impl<const N: usize> ChannelSchedule<N> {
+    /// Build a schedule from per-channel plans
+    /// Panics (at compile time when used in a const) unless every period is a
+    /// whole multiple of the fastest one
+    pub const fn from_plans(plans: &[SamplePlan; N]) -> Self {
+        let mut base_period_us = u32::MAX;
+        let mut max_conversion_time_us = 0;
+        let mut i = 0;
+        while i < N {
+            if plans[i].sample_period_us < base_period_us {
+                base_period_us = plans[i].sample_period_us;
+            }
+            if plans[i].conversion_time_us > max_conversion_time_us {
+                max_conversion_time_us = plans[i].conversion_time_us;
+            }
+            i += 1;
+        }
+
+        let mut periods_us = [0; N];
+        let mut dividers = [1; N];
+        let mut i = 0;
+        while i < N {
+            let period = plans[i].sample_period_us;
+            assert!(
+                period.is_multiple_of(base_period_us),
+                "Channel sample periods must be multiples of the fastest period"
+            );
+            periods_us[i] = period;
+            dividers[i] = period / base_period_us;
+            i += 1;
+        }
+
+        Self {
+            base_period_us,
+            periods_us,
+            dividers,
+            max_conversion_time_us,
+        }
+    }
+
+    /// Channels to sample on grid tick `tick`
+    pub fn due(&self, tick: u64) -> [bool; N] {
+        self.dividers
+            .map(|divider| tick.is_multiple_of(divider as u64))
+    }
+}
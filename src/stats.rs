@@ -0,0 +1,134 @@
+//! Per-channel summary statistics
+//!
+//! Accumulated in integer counts: sums are 64-bit and the variance is
+//! evaluated in 128-bit, so windows of any practical length cannot overflow.
+
+use crate::acquisition::Reading;
+
+/// Running min/max/mean/variance accumulator for one channel
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunningStats {
+    count: u32,
+    min: i32,
+    max: i32,
+    sum: i64,
+    sum_sq: u64,
+}
+
+/// Statistics of one channel over one window, in counts of 1/128°C
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelStats {
+    /// Valid (non-faulted) samples in the window
+    pub count: u32,
+    pub min: i32,
+    pub max: i32,
+    pub mean: i32,
+    /// Population standard deviation
+    pub stddev: u32,
+}
+
+impl RunningStats {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            min: 0,
+            max: 0,
+            sum: 0,
+            sum_sq: 0,
+        }
+    }
+
+    pub fn add(&mut self, counts: i32) {
+        if self.count == 0 {
+            self.min = counts;
+            self.max = counts;
+        } else {
+            self.min = self.min.min(counts);
+            self.max = self.max.max(counts);
+        }
+        self.count += 1;
+        self.sum += counts as i64;
+        self.sum_sq += (counts as i64 * counts as i64) as u64;
+    }
+
+    /// Statistics so far (all zero when no samples were added)
+    pub fn finish(&self) -> ChannelStats {
+        if self.count == 0 {
+            return ChannelStats::default();
+        }
+        let n = self.count as i128;
+        let sum = self.sum as i128;
+
+        // Round the mean to nearest
+        let mean = if sum >= 0 {
+            (sum + n / 2) / n
+        } else {
+            (sum - n / 2) / n
+        };
+
+        // n^2 * variance = n * sum(x^2) - sum(x)^2
+        let scaled_variance = (n * self.sum_sq as i128 - sum * sum).max(0) as u128;
+        let variance = scaled_variance / (n * n) as u128;
+
+        ChannelStats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: mean as i32,
+            stddev: (variance as u64).isqrt() as u32,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Statistics accumulators for every channel
+pub struct StatsBank<const N: usize> {
+    stats: [RunningStats; N],
+    window_start_us: Option<u64>,
+}
+
+impl<const N: usize> Default for StatsBank<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> StatsBank<N> {
+    pub const fn new() -> Self {
+        Self {
+            stats: [RunningStats::new(); N],
+            window_start_us: None,
+        }
+    }
+
+    /// Add a sample set; faulted readings are left out of the statistics
+    pub fn add(&mut self, readings: &[Option<Reading>; N]) {
+        for (stats, reading) in self.stats.iter_mut().zip(readings) {
+            let Some(reading) = reading else {
+                continue;
+            };
+            if self.window_start_us.is_none() {
+                self.window_start_us = Some(reading.timestamp_us);
+            }
+            if reading.is_valid() {
+                stats.add(reading.counts);
+            }
+        }
+    }
+
+    /// Time of the first sample in the current window
+    pub fn window_start_us(&self) -> Option<u64> {
+        self.window_start_us
+    }
+
+    /// Finish the window and start a new one
+    pub fn take(&mut self) -> [ChannelStats; N] {
+        let result = self.stats.map(|stats| stats.finish());
+        *self = Self::new();
+        result
+    }
+}
@@ -0,0 +1,204 @@
+//! Multi-board clock synchronisation
+//!
+//! One board is the master and its boot clock is the shared timebase. It
+//! can send a sync pulse on a GPIO at every whole `SYNC_PERIOD_US` of that
+//! timebase, broadcast its time in a UDP sync message, or both. Followers
+//! keep an offset from their own boot clock to the master's. Network
+//! messages give the absolute time to within the network latency, and
+//! pulses then trim the phase to within interrupt latency. Timestamps and
+//! the sample grid both follow the corrected clock.
+
+/// Interval between sync pulses and messages
+pub const SYNC_PERIOD_US: u64 = 1_000_000;
+
+/// Network corrections larger than this are applied at once, smaller ones
+/// are slewed in over a few messages to smooth out latency jitter
+pub const SYNC_STEP_THRESHOLD_US: i64 = 10_000;
+
+/// Fraction (1/N) of a small network error corrected per message
+const SYNC_SLEW_DIVISOR: i64 = 4;
+
+/// A follower is reported as in holdover after this many missed periods
+pub const SYNC_HOLDOVER_PERIODS: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncRole {
+    /// No synchronisation, timestamps are boot-relative
+    Standalone,
+    /// Provides the timebase for the other boards
+    Master,
+    /// Follows a master
+    Follower,
+}
+
+/// Sync role and the mechanisms it uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncConfig {
+    pub role: SyncRole,
+    /// Drive (master) or follow (follower) the sync pulse line
+    pub pulse: bool,
+    /// Send (master) or follow (follower) UDP sync messages
+    pub network: bool,
+}
+
+/// What last corrected the clock, as reported in telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum SyncSource {
+    /// Never synchronised, or standalone
+    None = 0,
+    /// This board is the master
+    Master = 1,
+    /// Last corrected by a network message
+    Network = 2,
+    /// Last corrected by a sync pulse
+    Pulse = 3,
+}
+
+/// Follower clock state
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSync {
+    offset_us: i64,
+    source: SyncSource,
+    last_error_us: i64,
+    last_update_us: Option<u64>,
+}
+
+impl Default for ClockSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClockSync {
+    pub const fn new() -> Self {
+        Self {
+            offset_us: 0,
+            source: SyncSource::None,
+            last_error_us: 0,
+            last_update_us: None,
+        }
+    }
+
+    /// Clock of a master, which is the timebase by definition
+    pub const fn master() -> Self {
+        Self {
+            offset_us: 0,
+            source: SyncSource::Master,
+            last_error_us: 0,
+            last_update_us: None,
+        }
+    }
+
+    /// Shared time minus local boot time
+    pub fn offset_us(&self) -> i64 {
+        self.offset_us
+    }
+
+    pub fn source(&self) -> SyncSource {
+        self.source
+    }
+
+    /// Error found at the last correction, before it was applied
+    pub fn last_error_us(&self) -> i64 {
+        self.last_error_us
+    }
+
+    /// Local time of the last correction
+    pub fn last_update_us(&self) -> Option<u64> {
+        self.last_update_us
+    }
+
+    /// True if a follower has gone too long without a correction
+    pub fn in_holdover(&self, local_us: u64) -> bool {
+        match self.source {
+            SyncSource::None => true,
+            SyncSource::Master => false,
+            _ => self.last_update_us.is_none_or(|last| {
+                local_us.saturating_sub(last) > SYNC_HOLDOVER_PERIODS * SYNC_PERIOD_US
+            }),
+        }
+    }
+
+    /// Convert a local boot time to shared time
+    pub fn to_synced(&self, local_us: u64) -> u64 {
+        (local_us as i64 + self.offset_us).max(0) as u64
+    }
+
+    /// Apply a network sync message sent at `master_us`, received at `local_us`
+    pub fn on_network(&mut self, master_us: u64, local_us: u64) {
+        let error = master_us as i64 - (local_us as i64 + self.offset_us);
+        let pulse_locked = self.source == SyncSource::Pulse && !self.in_holdover(local_us);
+
+        if pulse_locked {
+            // Pulses set the phase; only fix whole-period slips here
+            if error.abs() < SYNC_PERIOD_US as i64 / 2 {
+                return;
+            }
+            self.offset_us += error;
+        } else if self.source == SyncSource::None || error.abs() > SYNC_STEP_THRESHOLD_US {
+            self.offset_us += error;
+            self.source = SyncSource::Network;
+        } else {
+            self.offset_us += error / SYNC_SLEW_DIVISOR;
+            self.source = SyncSource::Network;
+        }
+        self.last_error_us = error;
+        self.last_update_us = Some(local_us);
+    }
+
+    /// Apply a sync pulse edge seen at `local_us`
+    /// The pulse marks a whole period of shared time; the nearest one is assumed
+    pub fn on_pulse(&mut self, local_us: u64) {
+        let period = SYNC_PERIOD_US as i64;
+        let synced = local_us as i64 + self.offset_us;
+        let nearest = (synced + period / 2).div_euclid(period) * period;
+        let error = nearest - synced;
+
+        self.offset_us += error;
+        self.last_error_us = error;
+        self.last_update_us = Some(local_us);
+        self.source = SyncSource::Pulse;
+    }
+}
+
+/// UDP sync message broadcast by the master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncMessage {
+    /// Incremented with every message
+    pub sequence: u32,
+    /// Master time when the message was sent, in microseconds
+    pub master_time_us: u64,
+}
+
+impl SyncMessage {
+    /// Encoded length in bytes
+    pub const LEN: usize = 16;
+
+    /// Little-endian wire format: packet tag, sequence, master time
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[0..4].copy_from_slice(&crate::PACKET_TAG_SYNC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.master_time_us.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a received message, rejecting anything else
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let tag = u32::from_le_bytes(bytes[0..4].try_into().ok()?);
+        if tag != crate::PACKET_TAG_SYNC {
+            return None;
+        }
+        Some(Self {
+            sequence: u32::from_le_bytes(bytes[4..8].try_into().ok()?),
+            master_time_us: u64::from_le_bytes(bytes[8..16].try_into().ok()?),
+        })
+    }
+}
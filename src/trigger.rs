@@ -0,0 +1,46 @@
+//! External trigger input
+//!
+//! Edges on the trigger input are timestamped and sent as marker events,
+//! and the first samples stored after each edge carry the `TRIGGER` quality
+//! flag, so data can be lined up with events such as ignition. While the trigger is
+//! active the firmware can also gate streaming or sample faster.
+
+/// What the firmware does while the trigger is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerAction {
+    /// Only send marker events
+    MarkOnly,
+    /// Stream data and summaries only while the trigger is active
+    GateStream,
+    /// Sample at the boost rates while the trigger is active
+    BoostRate,
+}
+
+/// Trigger input behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TriggerConfig {
+    /// Input level that means the trigger is active
+    pub active_high: bool,
+    /// Ignore further edges for this long after each accepted edge
+    pub debounce_us: u32,
+    pub action: TriggerAction,
+}
+
+impl TriggerConfig {
+    /// Whether an input level means the trigger is active
+    pub const fn is_active(&self, level_high: bool) -> bool {
+        level_high == self.active_high
+    }
+
+    /// Whether data and summaries are streamed in this trigger state
+    pub const fn streams(&self, active: bool) -> bool {
+        !matches!(self.action, TriggerAction::GateStream) || active
+    }
+
+    /// Whether the boost rates apply in this trigger state
+    pub const fn boosts(&self, active: bool) -> bool {
+        matches!(self.action, TriggerAction::BoostRate) && active
+    }
+}